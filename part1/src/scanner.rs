@@ -101,7 +101,7 @@ impl Iterator for Scanner {
     /// Implementation of iterator. Points worth noting in this API:
     /// - `Some(Ok(T))` indicates that the scanning happened with no errors
     /// - `Some(Error(T))` indicates that the scanner returned an error, and the
-    ///    caller may either ignore this error or abort scanning. (Warnings are printed)
+    ///   caller may either ignore this error or abort scanning. (Warnings are printed)
     /// - `None` indicates that the scanner has completed scanning the file and the
     ///   iterator may be discarded. It is crucial that this is not returned early.
     ///
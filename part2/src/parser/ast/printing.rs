@@ -108,7 +108,7 @@ impl<'a> PrettyPrint for PrintableReference<'a> {
             PrintableReference::Operator(o) => o.is_short(),
             PrintableReference::Type(t) => t.is_short(),
             PrintableReference::List(l) => l.iter().all(|e| e.is_short()),
-            PrintableReference::Option(o) => o.map_or(true, |o| o.is_short()),
+            PrintableReference::Option(o) => o.is_none_or(|o| o.is_short()),
         }
     }
 }
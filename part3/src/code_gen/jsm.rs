@@ -1,498 +1,949 @@
 //! EGRE 591 part3 - Nathan Rowan and Trevin Vaughan
 //!
-//! Code generation for the Jasmin target (JVM)
+//! Code generation for the Jasmin target (JVM). The generated [Class] is printed as Jasmin code.
+//!
+//! Chars are ints on the JVM, so char variables, parameters and return values are stored as
+//! ints. Semantic analysis marks where ints become chars, which is done with `i2c`.
+//!
+//! Locals are zeroed and arrays allocated when their block is entered, or by `<clinit>` for
+//! global arrays. Every read shares one `java.util.Scanner`, also created by `<clinit>`, since
+//! a scanner buffers more of standard input than it returns.
+//! Array accesses are marked with their source line, so that an out-of-bounds exception
+//! reports where it happened in the ToyC program.
+
+use super::{
+    jvm::{
+        Class, Condition, Constant, Field, FieldRef, FieldType, Instruction, Label, Method,
+        MethodDescriptor, MethodRef,
+    },
+    LabelMaker,
+};
+use crate::{
+    parser::ast::Operator,
+    semantic::typed::{
+        Expression, ExpressionKind, Program, Statement, Storage, SwitchCase, Type, Variable,
+    },
+};
+
+/// Generate code for a given ToyC program, which has been checked by semantic analysis
+pub fn generate_code(ast: &Program, file_name: &str, class_name: &str) -> Class {
+    let mut fields = Vec::new();
+    let mut methods = Vec::new();
+    let mut label_maker = LabelMaker::new();
 
-use std::intrinsics::mir::StaticMut;
+    // create <init> method
+    let object_init = MethodRef::new(
+        "java/lang/Object",
+        "<init>",
+        MethodDescriptor::new(vec![], None),
+    );
+    methods.push(Method::new(
+        false,
+        false,
+        "<init>",
+        MethodDescriptor::new(vec![], None),
+        vec![
+            Instruction::ALoad(0),
+            Instruction::InvokeSpecial(object_init),
+            Instruction::Return,
+        ],
+    ));
 
-use super::{Error, LabelMaker, SymbolTable};
-use crate::parser::ast::{Definition, Expression, Operator, Program, Statement, Type as AstType};
+    // create main method (jvm entrypoint)
+    let toyc_main = MethodRef::new(class_name, &method_name("main"), method_descriptor(0));
+    methods.push(Method::new(
+        true,
+        true,
+        "main",
+        MethodDescriptor::new(vec![FieldType::Array(Box::new(FieldType::string()))], None),
+        vec![
+            Instruction::InvokeStatic(toyc_main),
+            // the value returned by main is the exit code
+            Instruction::InvokeStatic(MethodRef::new(
+                "java/lang/System",
+                "exit",
+                MethodDescriptor::new(vec![FieldType::Int], None),
+            )),
+            Instruction::Return,
+        ],
+    ));
+
+    // the scanner used by read statements is stored as a static field of the class
+    fields.push(Field {
+        name: String::from(SCANNER_FIELD),
+        field_type: scanner_type(),
+    });
+    let input_stream = FieldType::Object(String::from("java/io/InputStream"));
+    let mut class_init = vec![
+        Instruction::New(String::from(SCANNER_CLASS)),
+        Instruction::Dup,
+        // get standard input
+        Instruction::GetStatic(FieldRef::new(
+            "java/lang/System",
+            "in",
+            input_stream.clone(),
+        )),
+        Instruction::InvokeSpecial(MethodRef::new(
+            SCANNER_CLASS,
+            "<init>",
+            MethodDescriptor::new(vec![input_stream], None),
+        )),
+        Instruction::PutStatic(FieldRef::new(class_name, SCANNER_FIELD, scanner_type())),
+    ];
+
+    // globals are stored as static fields of the class
+    for global in &ast.globals {
+        fields.push(Field {
+            name: global.name.to_owned(),
+            field_type: field_type(global.var_type),
+        });
+
+        // global arrays are allocated when the class is initialized
+        if let Some(length) = global.var_type.length() {
+            class_init.extend(new_array(global.var_type, length));
+            class_init.push(Instruction::PutStatic(FieldRef::new(
+                class_name,
+                &global.name,
+                field_type(global.var_type),
+            )));
+        }
+    }
 
-/// Generate code for a given ToyC program
-///
-/// # Errors
-///
-/// Generates semantic errors in the AST, see [Error].
-pub fn generate_code(
-    ast: &Program,
-    file_name: &str,
-    class_name: &str,
-    dump_table: bool,
-) -> Result<String, Error> {
-    let mut symbol_table = SymbolTable::new_global();
-    let mut code = String::new();
-    let mut method_count = 0;
-    let mut label_maker = LabelMaker::new();
+    // create <clinit> method
+    class_init.push(Instruction::Return);
+    methods.push(Method::new(
+        false,
+        true,
+        "<clinit>",
+        MethodDescriptor::new(vec![], None),
+        class_init,
+    ));
+
+    for function in &ast.functions {
+        // insert code generation
+        let mut code = generate_code_for_statement(
+            &function.body,
+            &mut label_maker,
+            class_name,
+            &mut Vec::new(),
+        );
+
+        // functions that run off the end return 0, like main in C
+        code.push(Instruction::IConst(0));
+        code.push(Instruction::IReturn);
+
+        methods.push(Method::new(
+            false,
+            true,
+            &method_name(&function.name),
+            method_descriptor(function.params.len()),
+            code,
+        ));
+    }
 
-    // file headers
-    code += "; created using EGRE-591 ToyC compiler by Nathan Rowan and Trevin Vaughan\n\n";
+    Class {
+        source: file_name.to_owned(),
+        name: class_name.to_owned(),
+        super_name: String::from("java/lang/Object"),
+        fields,
+        methods,
+    }
+}
 
-    code += &format!(".source {}\n", file_name);
-    code += &format!(".class {}\n", class_name);
-    code += ".super java/lang/Object\n\n";
+/// Class of the scanner used by read statements
+const SCANNER_CLASS: &str = "java/util/Scanner";
 
-    // create <init> method
-    code += &format!("; >> METHOD {} <<\n", method_count);
-    code += ".method <init>()V\n";
-    code += "    .limit stack 1\n";
-    code += "    .limit locals 1\n";
-    code += "    aload_0\n";
-    code += "    invokespecial java/lang/Object/<init>()V\n";
-    code += "    return\n";
-    code += ".end method\n\n";
-    method_count += 1;
+/// Name of the static field holding the scanner, which can't be a ToyC identifier
+const SCANNER_FIELD: &str = "toyc_scanner";
 
-    // create main method (jvm entrypoint)
-    code += ".method public static main([Ljava/lang/String;)V\n";
-    code += "    .limit stack 1\n";
-    code += "    .limit locals 1\n";
-    code += &format!("    invokestatic {}/toyc_main()I\n", class_name);
-    code += "    pop\n";
-    code += "    return\n";
-    code += ".end method\n\n";
-    method_count += 1;
-
-    code += "; begin ToyC code generation...\n\n";
-
-    for def in ast.0.iter() {
-        match def {
-            Definition::Func(id, return_type, args, body) => {
-                if id == "main" {
-                    // main must have signature int main()
-
-                    if !matches!(return_type, AstType::Int) {
-                        return Err(Error::InvalidReturn);
-                    }
+/// JVM type of the scanner used by read statements
+fn scanner_type() -> FieldType {
+    FieldType::Object(String::from(SCANNER_CLASS))
+}
 
-                    if !args.is_empty() {
-                        return Err(Error::InvalidSubroutineParameters);
-                    }
+/// Name of the JVM method generated for a ToyC function.
+///
+/// Every name is prefixed so that user functions can never collide with `main` (the JVM entrypoint)
+fn method_name(id: &str) -> String {
+    format!("toyc_{}", id)
+}
 
-                    // setup for new function
-                    code += &format!("; >> METHOD {} <<\n", method_count);
-                    symbol_table.new_func(id)?;
-
-                    // create fake main method as toyc runtime entrypoint
-                    code += ".method static toyc_main()I\n";
-                    code += "    .limit stack 999\n"; // calculating stack size is optional
-                    code += "    .limit locals 999\n";
-
-                    // insert code generation
-                    code += &generate_code_for_statement(
-                        body,
-                        &mut symbol_table,
-                        dump_table,
-                        &mut label_maker,
-                    )?;
-
-                    // wrap up new function
-                    code += ".end method\n\n";
-                    method_count += 1;
-                } else {
-                    // implementing functions other than main is extra credit...
-                    return Err(Error::NonMainFunction(id.to_owned()));
-                }
-            }
-            Definition::Var(id, _) => return Err(Error::GlobalVariable(id[0].to_owned())),
-        }
+/// JVM method descriptor of a ToyC function taking `params` integers and returning an integer
+fn method_descriptor(params: usize) -> MethodDescriptor {
+    MethodDescriptor::new(vec![FieldType::Int; params], Some(FieldType::Int))
+}
+
+/// JVM type of a variable, where chars are stored as ints
+fn field_type(var_type: Type) -> FieldType {
+    match var_type {
+        Type::Int | Type::Char => FieldType::Int,
+        Type::IntArray(_) => FieldType::Array(Box::new(FieldType::Int)),
+        Type::CharArray(_) => FieldType::Array(Box::new(FieldType::Char)),
+        Type::String => FieldType::string(),
     }
+}
 
-    code += "; end ToyC code generation\n";
+/// Allocates a zeroed array of the given type and length, leaving it on the stack
+fn new_array(array_type: Type, length: u32) -> Vec<Instruction> {
+    let element = match array_type {
+        Type::CharArray(_) => FieldType::Char,
+        _ => FieldType::Int,
+    };
+
+    vec![
+        Instruction::IConst(length as i32),
+        Instruction::NewArray(element),
+    ]
+}
 
-    if !symbol_table.get_function("main") {
-        return Err(Error::MissingMain);
-    }
+/// Loads `System.out` onto the stack
+fn get_stdout() -> Instruction {
+    Instruction::GetStatic(FieldRef::new(
+        "java/lang/System",
+        "out",
+        FieldType::Object(String::from("java/io/PrintStream")),
+    ))
+}
+
+/// Invokes a `PrintStream` method taking at most one argument and returning nothing
+fn invoke_print(name: &str, arg: Option<FieldType>) -> Instruction {
+    Instruction::InvokeVirtual(MethodRef::new(
+        "java/io/PrintStream",
+        name,
+        MethodDescriptor::new(arg.into_iter().collect(), None),
+    ))
+}
 
-    Ok(code)
+/// Where break and continue statements inside of a loop or switch jump to
+#[derive(Clone, Copy)]
+struct LoopLabels {
+    /// where the next iteration starts, after the body (switches have none)
+    next: Option<Label>,
+    /// the exit of the loop or switch
+    end: Label,
 }
 
 /// Generates code for a given statement in a ToyC program
 ///
-/// # Errors
-///
-/// Generates semantic errors in the AST, see [Error].
+/// `loops` is a stack of the labels of the enclosing loops and switches, innermost last.
 fn generate_code_for_statement(
     statement: &Statement,
-    scope: &mut SymbolTable,
-    dump_table: bool,
     label_maker: &mut LabelMaker,
-) -> Result<String, Error> {
-    let mut code = String::new();
+    class_name: &str,
+    loops: &mut Vec<LoopLabels>,
+) -> Vec<Instruction> {
+    let mut code = Vec::new();
 
     match statement {
         Statement::Expr(e) => {
-            let (new_code, is_int) = generate_code_for_expression(e, scope, label_maker)?;
-
-            if !is_int {
-                return Err(Error::IncompatibleTypes);
-            };
+            code.extend(generate_code_for_expression(e, label_maker, class_name));
+            code.push(Instruction::Pop); // discard the result
+        }
+        Statement::Break => {
+            // jump to the exit of the innermost loop or switch
+            let labels = loops
+                .last()
+                .expect("semantic analysis only allows break inside of a loop or switch");
 
-            code += &new_code;
-            code += "    pop\n"; // discard the result
+            code.push(Instruction::Goto(labels.end));
+        }
+        Statement::Continue => {
+            // jump to wherever the innermost loop carries on from, skipping over switches
+            let next = loops
+                .iter()
+                .rev()
+                .find_map(|labels| labels.next)
+                .expect("semantic analysis only allows continue inside of a loop");
+
+            code.push(Instruction::Goto(next));
         }
-        Statement::Break => return Err(Error::BreakStatement),
         Statement::Block(vars, statements) => {
-            // create a new scope
-            let mut scope = scope.new_scope();
-
-            // add each variable identifier to the scope
-            for (ids, ast_type) in vars {
-                if !matches!(ast_type, AstType::Int) {
-                    return Err(Error::TypeUnimplemented(*ast_type));
-                }
+            // zero the variables declared by the block and allocate its arrays, so that the
+            // verifier sees a value in every local on every path
+            for var in vars {
+                let Storage::Local(offset) = var.storage else {
+                    continue;
+                };
 
-                for id in ids {
-                    scope.new_var(id)?;
+                match var.var_type.length() {
+                    Some(length) => {
+                        code.extend(new_array(var.var_type, length));
+                        code.push(Instruction::AStore(offset));
+                    }
+                    None => {
+                        code.push(Instruction::IConst(0));
+                        code.push(Instruction::IStore(offset));
+                    }
                 }
             }
 
-            // print the symbol table
-            if dump_table {
-                println!("{:#?}", scope);
-            }
-
             // generate code for each statement
             for statement in statements {
-                code +=
-                    &generate_code_for_statement(statement, &mut scope, dump_table, label_maker)?;
+                code.extend(generate_code_for_statement(
+                    statement,
+                    label_maker,
+                    class_name,
+                    loops,
+                ));
             }
         }
         Statement::If(expression, statement, next_statement) => {
-            //generate code for boolean expression
-            let (expression_code, ndc) = generate_code_for_expression(expression, &scope, label_maker)?;
-            let mut scope = &mut scope.new_scope(); 
-            
             if let Some(else_statement) = next_statement {
-                let else_lable = label_maker.mk_label();
-                let end_lable = label_maker.mk_label();
-    
-                code += &expression_code;
-                
-                //else
-                code += &format!("    ifeq {}\n", else_lable);
-    
-                //if code
-                code += &generate_code_for_statement(statement, scope, dump_table, label_maker)?;
-                code += &format!("    goto {}\n", end_lable);
-    
-                code += &format!("    {}\n", else_lable);
-                code += &generate_code_for_statement(else_statement, scope, dump_table, label_maker)?;
-                code += &format!("    {}\n", end_lable);
+                let else_label = label_maker.mk_label();
+                let end_label = label_maker.mk_label();
+
+                // false: jump to else
+                code.extend(generate_code_for_condition(
+                    expression,
+                    label_maker,
+                    class_name,
+                    else_label,
+                    false,
+                ));
+
+                // if code
+                code.extend(generate_code_for_statement(
+                    statement,
+                    label_maker,
+                    class_name,
+                    loops,
+                ));
+                code.push(Instruction::Goto(end_label));
+
+                // else code
+                code.push(Instruction::Label(else_label));
+                code.extend(generate_code_for_statement(
+                    else_statement,
+                    label_maker,
+                    class_name,
+                    loops,
+                ));
+                code.push(Instruction::Label(end_label));
             } else {
-                let end_lable = label_maker.mk_label();
-    
-                code += &expression_code;
-                
-                //end
-                code += &format!("    ifeq {}\n", end_lable);
-    
-                //if code
-                code += &generate_code_for_statement(statement, scope, dump_table, label_maker)?;
-                code += &format!("    {}", end_lable);
+                let end_label = label_maker.mk_label();
+
+                // false: jump to end
+                code.extend(generate_code_for_condition(
+                    expression,
+                    label_maker,
+                    class_name,
+                    end_label,
+                    false,
+                ));
+
+                // if code
+                code.extend(generate_code_for_statement(
+                    statement,
+                    label_maker,
+                    class_name,
+                    loops,
+                ));
+                code.push(Instruction::Label(end_label));
             }
-            
-
-            
-        } 
+        }
         Statement::Null => (),
         Statement::Return(val) => {
-            if let Some(val) = val {
-                let (new_code, is_int) = generate_code_for_expression(val, scope, label_maker)?;
-
-                if !is_int {
-                    return Err(Error::IncompatibleTypes);
-                };
-
-                code += &new_code;
-                code += "    ireturn\n";
-            } else {
-                // all functions must return an int
-                return Err(Error::InvalidReturn);
-            }
+            code.extend(generate_code_for_expression(val, label_maker, class_name));
+            code.push(Instruction::IReturn);
         }
         Statement::While(expression, statement) => {
-            let (expression_code, ndc) = generate_code_for_expression(expression, &scope, label_maker)?;
-            let mut scope = &mut scope.new_scope(); 
-            let while_lable = label_maker.mk_label();
-            let end_lable = label_maker.mk_label();
-            
-            code += &format!("    {}\n", while_lable);
-            code += &expression_code;
-            
-            //end
-            code += &format!("    ifeq {}\n", end_lable);
-
-            //if code
-            code += &generate_code_for_statement(statement, scope, dump_table, label_maker)?;
-            code += &format!("    goto {}\n", while_lable);
-            code += &format!("    {}", end_lable);
-
-        },
-        Statement::Read(args) => {
-            let scanner = scope.current_offset;
-            scope.current_offset += 1;
-
-            // construct a scanner
-            code += "    new java/util/Scanner\n";
-            code += "    dup\n";
-            // get standard input
-            code += "    getstatic java/lang/System/in Ljava/io/InputStream;\n";
-            // initialize scanner
-            code += "    invokespecial java/util/Scanner/<init>(Ljava/io/InputStream;)V\n";
-            // store the scanner to the stack frame
-            code += &format!("    astore{}{}\n", sep(scanner), scanner);
+            let while_label = label_maker.mk_label();
+            let end_label = label_maker.mk_label();
+
+            code.push(Instruction::Label(while_label));
+
+            // false: jump to end
+            code.extend(generate_code_for_condition(
+                expression,
+                label_maker,
+                class_name,
+                end_label,
+                false,
+            ));
+
+            // loop body, where continue statements jump back to the condition
+            loops.push(LoopLabels {
+                next: Some(while_label),
+                end: end_label,
+            });
+            code.extend(generate_code_for_statement(
+                statement,
+                label_maker,
+                class_name,
+                loops,
+            ));
+            loops.pop();
+            code.push(Instruction::Goto(while_label));
+            code.push(Instruction::Label(end_label));
+        }
+        Statement::DoWhile(statement, expression) => {
+            let do_label = label_maker.mk_label();
+            let condition_label = label_maker.mk_label();
+            let end_label = label_maker.mk_label();
+
+            // loop body, where continue statements jump to the condition
+            code.push(Instruction::Label(do_label));
+            loops.push(LoopLabels {
+                next: Some(condition_label),
+                end: end_label,
+            });
+            code.extend(generate_code_for_statement(
+                statement,
+                label_maker,
+                class_name,
+                loops,
+            ));
+            loops.pop();
+
+            // true: jump back to the body
+            code.push(Instruction::Label(condition_label));
+            code.extend(generate_code_for_condition(
+                expression,
+                label_maker,
+                class_name,
+                do_label,
+                true,
+            ));
+            code.push(Instruction::Label(end_label));
+        }
+        Statement::For(init, condition, step, statement) => {
+            let for_label = label_maker.mk_label();
+            let step_label = label_maker.mk_label();
+            let end_label = label_maker.mk_label();
+
+            if let Some(init) = init {
+                code.extend(generate_code_for_expression(init, label_maker, class_name));
+                code.push(Instruction::Pop); // discard the result
+            }
 
-            for arg in args {
-                let var = scope.get_variable(arg)?;
+            // false: jump to end, a missing condition is always true
+            code.push(Instruction::Label(for_label));
+            if let Some(condition) = condition {
+                code.extend(generate_code_for_condition(
+                    condition,
+                    label_maker,
+                    class_name,
+                    end_label,
+                    false,
+                ));
+            }
 
+            // loop body, where continue statements jump to the step
+            loops.push(LoopLabels {
+                next: Some(step_label),
+                end: end_label,
+            });
+            code.extend(generate_code_for_statement(
+                statement,
+                label_maker,
+                class_name,
+                loops,
+            ));
+            loops.pop();
+
+            code.push(Instruction::Label(step_label));
+            if let Some(step) = step {
+                code.extend(generate_code_for_expression(step, label_maker, class_name));
+                code.push(Instruction::Pop); // discard the result
+            }
+            code.push(Instruction::Goto(for_label));
+            code.push(Instruction::Label(end_label));
+        }
+        Statement::Switch(expression, cases) => {
+            let end_label = label_maker.mk_label();
+            let case_labels: Vec<Label> = cases.iter().map(|_| label_maker.mk_label()).collect();
+            // values without a case skip the whole switch, unless there is a default case
+            let default_label = cases
+                .iter()
+                .position(|case| case.value.is_none())
+                .map_or(end_label, |i| case_labels[i]);
+
+            code.extend(generate_code_for_expression(
+                expression,
+                label_maker,
+                class_name,
+            ));
+            code.extend(switch_instructions(cases, &case_labels, default_label));
+
+            // cases are placed in order, so that each falls through to the next
+            loops.push(LoopLabels {
+                next: None,
+                end: end_label,
+            });
+            for (case, label) in cases.iter().zip(&case_labels) {
+                code.push(Instruction::Label(*label));
+                for statement in &case.statements {
+                    code.extend(generate_code_for_statement(
+                        statement,
+                        label_maker,
+                        class_name,
+                        loops,
+                    ));
+                }
+            }
+            loops.pop();
+            code.push(Instruction::Label(end_label));
+        }
+        Statement::Read(vars) => {
+            for var in vars {
                 // load the scanner
-                code += &format!("    aload{}{}\n", sep(scanner), scanner);
-                // read an integer
-                code += "    invokevirtual java/util/Scanner/nextInt()I\n";
-                // store the integer
-                code += &format!("    istore{}{}\n", sep(var), var);
+                code.push(Instruction::GetStatic(FieldRef::new(
+                    class_name,
+                    SCANNER_FIELD,
+                    scanner_type(),
+                )));
+
+                if var.var_type == Type::Char {
+                    // read a word, and take its first character
+                    code.push(Instruction::InvokeVirtual(MethodRef::new(
+                        SCANNER_CLASS,
+                        "next",
+                        MethodDescriptor::new(vec![], Some(FieldType::string())),
+                    )));
+                    code.push(Instruction::IConst(0));
+                    code.push(Instruction::InvokeVirtual(MethodRef::new(
+                        "java/lang/String",
+                        "charAt",
+                        MethodDescriptor::new(vec![FieldType::Int], Some(FieldType::Char)),
+                    )));
+                } else {
+                    // read an integer
+                    code.push(Instruction::InvokeVirtual(MethodRef::new(
+                        SCANNER_CLASS,
+                        "nextInt",
+                        MethodDescriptor::new(vec![], Some(FieldType::Int)),
+                    )));
+                }
+
+                // store the value
+                code.push(store_variable(var, class_name));
             }
         }
         Statement::Write(expressions) => {
             for e in expressions {
-                let (new_code, is_int) = generate_code_for_expression(e, scope, label_maker)?;
-
-                code += "    getstatic java/lang/System/out Ljava/io/PrintStream;\n";
+                code.push(get_stdout());
 
-                code += &new_code;
+                code.extend(generate_code_for_expression(e, label_maker, class_name));
 
-                if is_int {
-                    code += "    invokevirtual java/io/PrintStream/print(I)V\n";
-                } else {
-                    code += "    invokevirtual java/io/PrintStream/print(Ljava/lang/String;)V\n";
-                }
+                let arg = match e.expr_type {
+                    Type::String => FieldType::string(),
+                    Type::Char => FieldType::Char,
+                    Type::Int => FieldType::Int,
+                    Type::IntArray(_) | Type::CharArray(_) => {
+                        unreachable!("arrays cannot be written")
+                    }
+                };
+                code.push(invoke_print("print", Some(arg)));
             }
         }
         Statement::Newline => {
             // get standard output
-            code += "    getstatic java/lang/System/out Ljava/io/PrintStream;\n";
+            code.push(get_stdout());
             // print a newline
-            code += "    invokevirtual java/io/PrintStream/println()V\n";
+            code.push(invoke_print("println", None));
         }
     }
 
-    Ok(code)
+    code
 }
 
-/// Creates a separator for jvm instructions such as `astore_1`
-fn sep(offset: usize) -> char {
-    if offset < 4 {
-        '_'
+/// Jumps to the label of the case matching the value on top of the stack.
+///
+/// Like javac, a `tableswitch` is used when the case values are dense enough that a table
+/// of every value in their range isn't much larger than a `lookupswitch` of only the values.
+fn switch_instructions(cases: &[SwitchCase], labels: &[Label], default: Label) -> Vec<Instruction> {
+    let mut pairs: Vec<(i32, Label)> = cases
+        .iter()
+        .zip(labels)
+        .filter_map(|(case, label)| Some((case.value?, *label)))
+        .collect();
+    pairs.sort_by_key(|(value, _)| *value);
+
+    // with nothing to compare against, the value is discarded (Jasmin rejects empty switches)
+    let (Some((low, _)), Some((high, _))) = (pairs.first().copied(), pairs.last().copied()) else {
+        return vec![Instruction::Pop, Instruction::Goto(default)];
+    };
+
+    // space is counted in words, and time is weighted 3 times as much
+    let count = pairs.len() as i64;
+    let table_cost = 4 + (high as i64 - low as i64 + 1) + 3 * 3;
+    let lookup_cost = 3 + 2 * count + 3 * count;
+
+    if table_cost <= lookup_cost {
+        // values in the range without a case jump to the default
+        let table = (low..=high)
+            .map(|value| {
+                pairs
+                    .binary_search_by_key(&value, |(value, _)| *value)
+                    .map_or(default, |i| pairs[i].1)
+            })
+            .collect();
+
+        vec![Instruction::TableSwitch(low, table, default)]
     } else {
-        ' '
+        vec![Instruction::LookupSwitch(pairs, default)]
     }
 }
 
+/// Loads an integer variable onto the stack
+fn load_variable(var: &Variable, class_name: &str) -> Instruction {
+    match var.storage {
+        Storage::Local(offset) => Instruction::ILoad(offset),
+        Storage::Static => {
+            Instruction::GetStatic(FieldRef::new(class_name, &var.name, FieldType::Int))
+        }
+    }
+}
+
+/// Stores the integer on top of the stack to a variable
+fn store_variable(var: &Variable, class_name: &str) -> Instruction {
+    match var.storage {
+        Storage::Local(offset) => Instruction::IStore(offset),
+        Storage::Static => {
+            Instruction::PutStatic(FieldRef::new(class_name, &var.name, FieldType::Int))
+        }
+    }
+}
+
+/// Loads a reference to an array variable onto the stack
+fn load_array(var: &Variable, class_name: &str) -> Instruction {
+    match var.storage {
+        Storage::Local(offset) => Instruction::ALoad(offset),
+        Storage::Static => Instruction::GetStatic(FieldRef::new(
+            class_name,
+            &var.name,
+            field_type(var.var_type),
+        )),
+    }
+}
+
+/// Marks the following instructions as coming from the line an expression starts on
+fn line(expression: &Expression) -> Instruction {
+    Instruction::Line(expression.span.start.line + 1)
+}
+
 /// Generates code for expressions. Leaves the result on the stack to be used in statements
-///
-/// Returns the code and a bool representing whether it's an integer or not
 fn generate_code_for_expression(
     expression: &Expression,
-    scope: &SymbolTable,
     label_maker: &mut LabelMaker,
-) -> Result<(String, bool, ), Error> {
-    let mut code = String::new();
+    class_name: &str,
+) -> Vec<Instruction> {
+    let mut code = Vec::new();
 
-    match expression {
+    match &expression.kind {
         // load a number constant
-        Expression::Number(n) => {
-            // this is a catch-all for numeric constants, including negatives and large integers
-            // jasmin probably translates this to real jvm ops
-            code += &format!("    ldc {}\n", n);
-        }
-        // load an identifier value
-        Expression::Identifier(id) => {
-            let offset = scope.get_variable(id)?;
-            code += &format!("    iload{}{}\n", sep(offset), offset);
-        }
-        // char literals are unimplemented
-        Expression::CharLiteral(c) => return Err(Error::CharLiteral(*c)),
+        ExpressionKind::Number(n) => code.push(Instruction::IConst(*n)),
+        // load a variable value
+        ExpressionKind::Variable(var) => code.push(load_variable(var, class_name)),
+        // load a char constant, which is just a number
+        ExpressionKind::CharLiteral(c) => code.push(Instruction::IConst(*c as i32)),
         // load a string literal
-        Expression::StringLiteral(s) => {
-            code += &format!("    ldc \"{}\"\n", s);
+        ExpressionKind::StringLiteral(s) => {
+            code.push(Instruction::Ldc(Constant::String(s.to_owned())));
+        }
+        // call a function
+        ExpressionKind::FuncCall(id, args) => {
+            // push each argument from left to right
+            for arg in args {
+                code.extend(generate_code_for_expression(arg, label_maker, class_name));
+            }
+
+            // the result is left on the stack
+            code.push(Instruction::InvokeStatic(MethodRef::new(
+                class_name,
+                &method_name(id),
+                method_descriptor(args.len()),
+            )));
+        }
+        ExpressionKind::Assign(var, rhs) => {
+            code.extend(generate_code_for_expression(rhs, label_maker, class_name));
+            // duplicate the result
+            code.push(Instruction::Dup);
+            // store one copy to the stack frame, leaving the other on the operator stack
+            code.push(store_variable(var, class_name));
+        }
+        // load an element of an array
+        ExpressionKind::Index(var, index) => {
+            code.push(load_array(var, class_name));
+            code.extend(generate_code_for_expression(index, label_maker, class_name));
+            code.push(line(expression));
+            code.push(match var.var_type {
+                Type::CharArray(_) => Instruction::CALoad,
+                _ => Instruction::IALoad,
+            });
+        }
+        ExpressionKind::AssignIndex(var, index, rhs) => {
+            code.push(load_array(var, class_name));
+            code.extend(generate_code_for_expression(index, label_maker, class_name));
+            code.extend(generate_code_for_expression(rhs, label_maker, class_name));
+            // keep a copy of the result below the array and the index
+            code.push(Instruction::DupX2);
+            code.push(line(expression));
+            code.push(match var.var_type {
+                Type::CharArray(_) => Instruction::CAStore,
+                _ => Instruction::IAStore,
+            });
+        }
+        // comparisons and logical operators are evaluated with jumps
+        ExpressionKind::Expr(op, _, _) if is_boolean(*op) => {
+            code.extend(generate_boolean_value(expression, label_maker, class_name));
         }
-        // function calls are unimplemented
-        Expression::FuncCall(id, _) => return Err(Error::NonMainFunction(id.to_owned())),
         // binary operation expressions
-        Expression::Expr(op, lhs, rhs) => {
-            // assign statements are treated differently
-            if matches!(op, Operator::Assign) {
-                match &**lhs {
-                    // lhs must be an id
-                    Expression::Identifier(id) => {
-                        // get the variable from the scope
-                        let offset = scope.get_variable(id)?;
-                        // generate code for the rhs
-                        let (rhs_code, is_int) =
-                            generate_code_for_expression(rhs, scope, label_maker)?;
-
-                        if !is_int {
-                            return Err(Error::IncompatibleTypes);
-                        };
-
-                        code += &rhs_code;
-                        // duplicate the result
-                        code += "    dup\n";
-                        // store one copy to the stack frame, leaving the other on the operator stack
-                        code += &format!("    istore{}{}\n", sep(offset), offset);
-                    }
-                    _ => return Err(Error::InvalidAssign),
-                }
-            } else {
-                // generate code for the left and right sides
-                let (lhs_code, lhs_is_int) = generate_code_for_expression(lhs, scope, label_maker)?;
-                let (rhs_code, rhs_is_int) = generate_code_for_expression(rhs, scope, label_maker)?;
+        ExpressionKind::Expr(op, lhs, rhs) => {
+            // generate code for the left and right sides
+            code.extend(generate_code_for_expression(lhs, label_maker, class_name));
+            code.extend(generate_code_for_expression(rhs, label_maker, class_name));
+
+            // consume the values
+            code.push(match op {
+                Operator::Add => Instruction::IAdd,
+                Operator::Sub => Instruction::ISub,
+                Operator::Mul => Instruction::IMul,
+                Operator::Div => Instruction::IDiv,
+                Operator::Mod => Instruction::IRem,
+                Operator::BitAnd => Instruction::IAnd,
+                Operator::BitOr => Instruction::IOr,
+                Operator::BitXor => Instruction::IXor,
+                Operator::Shl => Instruction::IShl,
+                Operator::Shr => Instruction::IShr,
+                _ => unreachable!(),
+            });
+        }
+        // negate an integer
+        ExpressionKind::Minus(e) => {
+            code.extend(generate_code_for_expression(e, label_maker, class_name));
+            code.push(Instruction::INeg);
+        }
+        // negate a boolean
+        ExpressionKind::Not(_) => {
+            code.extend(generate_boolean_value(expression, label_maker, class_name));
+        }
+        // complement an integer by flipping every bit with -1
+        ExpressionKind::BitNot(e) => {
+            code.extend(generate_code_for_expression(e, label_maker, class_name));
+            code.push(Instruction::IConst(-1));
+            code.push(Instruction::IXor);
+        }
+        // narrow a number to a char
+        ExpressionKind::ToChar(e) => {
+            code.extend(generate_code_for_expression(e, label_maker, class_name));
+            code.push(Instruction::I2C);
+        }
+        // evaluate only one side, using jumps like an if statement
+        ExpressionKind::Conditional(condition, true_exp, false_exp) => {
+            // label for the false side
+            let else_label = label_maker.mk_label();
+            // label after the conditional
+            let end_label = label_maker.mk_label();
+
+            code.extend(generate_code_for_condition(
+                condition,
+                label_maker,
+                class_name,
+                else_label,
+                false,
+            ));
+            // true: load the true side and jump to end
+            code.extend(generate_code_for_expression(
+                true_exp,
+                label_maker,
+                class_name,
+            ));
+            code.push(Instruction::Goto(end_label));
+            // false: load the false side
+            code.push(Instruction::Label(else_label));
+            code.extend(generate_code_for_expression(
+                false_exp,
+                label_maker,
+                class_name,
+            ));
+            // end
+            code.push(Instruction::Label(end_label));
+        }
+    }
 
-                if !lhs_is_int || !rhs_is_int {
-                    return Err(Error::IncompatibleTypes);
-                };
+    code
+}
 
-                code += &lhs_code;
-                code += &rhs_code;
-
-                // consume the values
-                match op {
-                    Operator::Add => code += "    iadd\n",
-                    Operator::Sub => code += "    isub\n",
-                    Operator::Mul => code += "    imul\n",
-                    Operator::Div => code += "    idiv\n",
-                    Operator::Mod => code += "    irem\n",
-                    Operator::BoolOr => code += "    ior\n",
-                    Operator::BoolAnd => code += "    iand\n",
-                    Operator::LtEq => {
-                        // label if jump taken
-                        let if_label = label_maker.mk_label();
-                        // label after conditional
-                        let end_label = label_maker.mk_label();
-                        // do comparison
-                        code += &format!("    if_icmple {}\n", if_label);
-                        // false: load 0 and jump to end
-                        code += "    iconst_0\n";
-                        code += &format!("    goto {}\n", end_label);
-                        // true: load 1
-                        code += &format!("{}:\n", if_label);
-                        code += "    iconst_1\n";
-                        // end
-                        code += &format!("{}:\n", end_label);
-                    }
-                    Operator::Lt => {
-                        // label if jump taken
-                        let if_label = label_maker.mk_label();
-                        // label after conditional
-                        let end_label = label_maker.mk_label();
-                        // do comparison
-                        code += &format!("    if_icmplt {}\n", if_label);
-                        // false: load 0 and jump to end
-                        code += "    iconst_0\n";
-                        code += &format!("    goto {}\n", end_label);
-                        // true: load 1
-                        code += &format!("{}:\n", if_label);
-                        code += "    iconst_1\n";
-                        // end
-                        code += &format!("{}:\n", end_label);
-                    }
-                    Operator::Eq => {
-                        // label if jump taken
-                        let if_label = label_maker.mk_label();
-                        // label after conditional
-                        let end_label = label_maker.mk_label();
-                        // do comparison
-                        code += &format!("    if_icmpeq {}\n", if_label);
-                        // false: load 0 and jump to end
-                        code += "    iconst_0\n";
-                        code += &format!("    goto {}\n", end_label);
-                        // true: load 1
-                        code += &format!("{}:\n", if_label);
-                        code += "    iconst_1\n";
-                        // end
-                        code += &format!("{}:\n", end_label);
-                    }
-                    Operator::Gt => {
-                        // label if jump taken
-                        let if_label = label_maker.mk_label();
-                        // label after conditional
-                        let end_label = label_maker.mk_label();
-                        // do comparison
-                        code += &format!("    if_icmpgt {}\n", if_label);
-                        // false: load 0 and jump to end
-                        code += "    iconst_0\n";
-                        code += &format!("    goto {}\n", end_label);
-                        // true: load 1
-                        code += &format!("{}:\n", if_label);
-                        code += "    iconst_1\n";
-                        // end
-                        code += &format!("{}:\n", end_label);
-                    }
-                    Operator::GtEq => {
-                        // label if jump taken
-                        let if_label = label_maker.mk_label();
-                        // label after conditional
-                        let end_label = label_maker.mk_label();
-                        // do comparison
-                        code += &format!("    if_icmpge {}\n", if_label);
-                        // false: load 0 and jump to end
-                        code += "    iconst_0\n";
-                        code += &format!("    goto {}\n", end_label);
-                        // true: load 1
-                        code += &format!("{}:\n", if_label);
-                        code += "    iconst_1\n";
-                        // end
-                        code += &format!("{}:\n", end_label);
-                    }
-                    Operator::Neq => {
-                        // label if jump taken
-                        let if_label = label_maker.mk_label();
-                        // label after conditional
-                        let end_label = label_maker.mk_label();
-                        // do comparison
-                        code += &format!("    if_icmpne {}\n", if_label);
-                        // false: load 0 and jump to end
-                        code += "    iconst_0\n";
-                        code += &format!("    goto {}\n", end_label);
-                        // true: load 1
-                        code += &format!("{}:\n", if_label);
-                        code += "    iconst_1\n";
-                        // end
-                        code += &format!("{}:\n", end_label);
-                    }
-                    Operator::Assign => unreachable!(),
-                }
+/// Whether an operator produces a boolean (0 or 1) using jumps
+fn is_boolean(op: Operator) -> bool {
+    matches!(op, Operator::BoolOr | Operator::BoolAnd) || comparison(op).is_some()
+}
+
+/// Jump condition of a comparison operator
+fn comparison(op: Operator) -> Option<Condition> {
+    match op {
+        Operator::LtEq => Some(Condition::Le),
+        Operator::Lt => Some(Condition::Lt),
+        Operator::Eq => Some(Condition::Eq),
+        Operator::Gt => Some(Condition::Gt),
+        Operator::GtEq => Some(Condition::Ge),
+        Operator::Neq => Some(Condition::Ne),
+        _ => None,
+    }
+}
+
+/// Generates code for a boolean expression, leaving 0 or 1 on the stack
+fn generate_boolean_value(
+    expression: &Expression,
+    label_maker: &mut LabelMaker,
+    class_name: &str,
+) -> Vec<Instruction> {
+    let mut code = Vec::new();
+
+    // label if the expression is true
+    let true_label = label_maker.mk_label();
+    // label after conditional
+    let end_label = label_maker.mk_label();
+
+    code.extend(generate_code_for_condition(
+        expression,
+        label_maker,
+        class_name,
+        true_label,
+        true,
+    ));
+    // false: load 0 and jump to end
+    code.push(Instruction::IConst(0));
+    code.push(Instruction::Goto(end_label));
+    // true: load 1
+    code.push(Instruction::Label(true_label));
+    code.push(Instruction::IConst(1));
+    // end
+    code.push(Instruction::Label(end_label));
+
+    code
+}
+
+/// Generates code that jumps to `target` if the truth value of the expression equals `jump_if`,
+/// falling through otherwise. Nothing is left on the stack.
+///
+/// The right hand side of `&&` and `||` is skipped when the left hand side decides the result.
+fn generate_code_for_condition(
+    expression: &Expression,
+    label_maker: &mut LabelMaker,
+    class_name: &str,
+    target: Label,
+    jump_if: bool,
+) -> Vec<Instruction> {
+    let mut code = Vec::new();
+
+    match &expression.kind {
+        ExpressionKind::Expr(Operator::BoolAnd, lhs, rhs) => {
+            if jump_if {
+                // both sides must be true to jump, skip the rhs if the lhs is false
+                let skip_label = label_maker.mk_label();
+                code.extend(generate_code_for_condition(
+                    lhs,
+                    label_maker,
+                    class_name,
+                    skip_label,
+                    false,
+                ));
+                code.extend(generate_code_for_condition(
+                    rhs,
+                    label_maker,
+                    class_name,
+                    target,
+                    true,
+                ));
+                code.push(Instruction::Label(skip_label));
+            } else {
+                // either side being false jumps
+                code.extend(generate_code_for_condition(
+                    lhs,
+                    label_maker,
+                    class_name,
+                    target,
+                    false,
+                ));
+                code.extend(generate_code_for_condition(
+                    rhs,
+                    label_maker,
+                    class_name,
+                    target,
+                    false,
+                ));
             }
         }
-        // negate an integer
-        Expression::Minus(e) => {
-            let (new_code, is_int) = generate_code_for_expression(e, scope, label_maker)?;
+        ExpressionKind::Expr(Operator::BoolOr, lhs, rhs) => {
+            if jump_if {
+                // either side being true jumps
+                code.extend(generate_code_for_condition(
+                    lhs,
+                    label_maker,
+                    class_name,
+                    target,
+                    true,
+                ));
+                code.extend(generate_code_for_condition(
+                    rhs,
+                    label_maker,
+                    class_name,
+                    target,
+                    true,
+                ));
+            } else {
+                // both sides must be false to jump, skip the rhs if the lhs is true
+                let skip_label = label_maker.mk_label();
+                code.extend(generate_code_for_condition(
+                    lhs,
+                    label_maker,
+                    class_name,
+                    skip_label,
+                    true,
+                ));
+                code.extend(generate_code_for_condition(
+                    rhs,
+                    label_maker,
+                    class_name,
+                    target,
+                    false,
+                ));
+                code.push(Instruction::Label(skip_label));
+            }
+        }
+        ExpressionKind::Expr(op, lhs, rhs) if comparison(*op).is_some() => {
+            let condition = comparison(*op).unwrap();
 
-            if !is_int {
-                return Err(Error::IncompatibleTypes);
-            };
+            // generate code for the left and right sides
+            code.extend(generate_code_for_expression(lhs, label_maker, class_name));
+            code.extend(generate_code_for_expression(rhs, label_maker, class_name));
 
-            code += &new_code;
-            code += "    ineg\n";
+            // do comparison
+            let condition = if jump_if {
+                condition
+            } else {
+                condition.negate()
+            };
+            code.push(Instruction::IfICmp(condition, target));
         }
-        // negate a boolean
-        Expression::Not(e) => {
-            let is_zero = label_maker.mk_label();
-            
-            code += &format!("    ifeq {}\n", is_zero);
-            let (new_code, is_int) = generate_code_for_expression(e, scope, label_maker)?;
-
-            if !is_int {
-                return Err(Error::IncompatibleTypes);
+        // negation flips the condition
+        ExpressionKind::Not(e) => {
+            code.extend(generate_code_for_condition(
+                e,
+                label_maker,
+                class_name,
+                target,
+                !jump_if,
+            ));
+        }
+        // any other integer is true when it is non-zero
+        _ => {
+            code.extend(generate_code_for_expression(
+                expression,
+                label_maker,
+                class_name,
+            ));
+
+            let condition = if jump_if {
+                Condition::Ne
+            } else {
+                Condition::Eq
             };
-
-            code += &format!("    {}\n", is_zero);
+            code.push(Instruction::If(condition, target));
         }
     }
 
-    let integer = !matches!(
-        expression,
-        Expression::CharLiteral(_) | Expression::StringLiteral(_)
-    );
-
-    Ok((code, integer))
+    code
 }
@@ -0,0 +1,424 @@
+//! EGRE 591 part3 - Nathan Rowan and Trevin Vaughan
+//!
+//! In-memory representation of the JVM classes produced by code generation.
+//! Classes are rendered as Jasmin assembly through their [Display] implementation,
+//! or written as class files with [Class::to_bytes].
+
+use std::fmt::Display;
+
+mod class_file;
+mod frames;
+mod limits;
+mod printing;
+
+/// Labels, which are the targets of jumps within a method
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Label(pub usize);
+
+/// Types of values as they appear in JVM descriptors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldType {
+    /// `I`
+    Int,
+    /// `C`
+    Char,
+    /// `L<class>;`
+    Object(String),
+    /// `[<type>`
+    Array(Box<FieldType>),
+}
+
+impl FieldType {
+    /// Shorthand for `java/lang/String`
+    pub fn string() -> Self {
+        Self::Object(String::from("java/lang/String"))
+    }
+}
+
+impl Display for FieldType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldType::Int => write!(f, "I"),
+            FieldType::Char => write!(f, "C"),
+            FieldType::Object(class) => write!(f, "L{};", class),
+            FieldType::Array(element) => write!(f, "[{}", element),
+        }
+    }
+}
+
+/// Parameter and return types of a method
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodDescriptor {
+    /// types of the parameters
+    pub params: Vec<FieldType>,
+    /// return type, where [None] is void
+    pub ret: Option<FieldType>,
+}
+
+impl MethodDescriptor {
+    /// Construct a new descriptor
+    pub fn new(params: Vec<FieldType>, ret: Option<FieldType>) -> Self {
+        Self { params, ret }
+    }
+}
+
+impl Display for MethodDescriptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(")?;
+        for param in &self.params {
+            write!(f, "{}", param)?;
+        }
+        write!(f, ")")?;
+        match &self.ret {
+            Some(ret) => write!(f, "{}", ret),
+            None => write!(f, "V"),
+        }
+    }
+}
+
+/// A reference to a field of some class
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldRef {
+    /// class the field belongs to
+    pub class: String,
+    /// name of the field
+    pub name: String,
+    /// type of the field
+    pub field_type: FieldType,
+}
+
+impl FieldRef {
+    /// Construct a new field reference
+    pub fn new(class: &str, name: &str, field_type: FieldType) -> Self {
+        Self {
+            class: class.to_owned(),
+            name: name.to_owned(),
+            field_type,
+        }
+    }
+}
+
+/// A reference to a method of some class
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodRef {
+    /// class the method belongs to
+    pub class: String,
+    /// name of the method
+    pub name: String,
+    /// parameter and return types of the method
+    pub descriptor: MethodDescriptor,
+}
+
+impl MethodRef {
+    /// Construct a new method reference
+    pub fn new(class: &str, name: &str, descriptor: MethodDescriptor) -> Self {
+        Self {
+            class: class.to_owned(),
+            name: name.to_owned(),
+            descriptor,
+        }
+    }
+}
+
+/// Conditions used by conditional jumps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    /// ==
+    Eq,
+    /// !=
+    Ne,
+    /// <
+    Lt,
+    /// >=
+    Ge,
+    /// >
+    Gt,
+    /// <=
+    Le,
+}
+
+impl Condition {
+    /// The condition that holds exactly when this one doesn't
+    pub fn negate(self) -> Self {
+        match self {
+            Condition::Eq => Condition::Ne,
+            Condition::Ne => Condition::Eq,
+            Condition::Lt => Condition::Ge,
+            Condition::Ge => Condition::Lt,
+            Condition::Gt => Condition::Le,
+            Condition::Le => Condition::Gt,
+        }
+    }
+}
+
+/// Constants which can be loaded from the constant pool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Constant {
+    /// An integer
+    Int(i32),
+    /// A string
+    String(String),
+}
+
+/// The subset of JVM instructions used by the code generator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    /// Marks the position of a label (not an actual instruction)
+    Label(Label),
+    /// Marks the following instructions as coming from a line of the source file, counting
+    /// from 1 (not an actual instruction)
+    Line(usize),
+    /// Push an integer constant
+    IConst(i32),
+    /// Push a constant from the constant pool
+    Ldc(Constant),
+    /// Push an integer local variable
+    ILoad(usize),
+    /// Pop an integer into a local variable
+    IStore(usize),
+    /// Push a reference local variable
+    ALoad(usize),
+    /// Pop a reference into a local variable
+    AStore(usize),
+    /// Add two integers
+    IAdd,
+    /// Subtract two integers
+    ISub,
+    /// Multiply two integers
+    IMul,
+    /// Divide two integers
+    IDiv,
+    /// Remainder of two integers
+    IRem,
+    /// Negate an integer
+    INeg,
+    /// Bitwise and of two integers
+    IAnd,
+    /// Bitwise or of two integers
+    IOr,
+    /// Bitwise xor of two integers
+    IXor,
+    /// Shift an integer left
+    IShl,
+    /// Shift an integer right, keeping its sign
+    IShr,
+    /// Truncate an integer to a char
+    I2C,
+    /// Duplicate the top of the stack
+    Dup,
+    /// Duplicate the top of the stack, inserting the copy below the two values under it
+    DupX2,
+    /// Discard the top of the stack
+    Pop,
+    /// Compare an integer against zero and jump if the condition holds
+    If(Condition, Label),
+    /// Compare two integers and jump if the condition holds
+    IfICmp(Condition, Label),
+    /// Jump unconditionally
+    Goto(Label),
+    /// Pop an integer and jump to the label at its offset from the lowest value, or to the
+    /// default label when it is out of range
+    TableSwitch(i32, Vec<Label>, Label),
+    /// Pop an integer and jump to the label paired with it, or to the default label when
+    /// there is none. The pairs are sorted by value
+    LookupSwitch(Vec<(i32, Label)>, Label),
+    /// Return an integer
+    IReturn,
+    /// Return void
+    Return,
+    /// Push a static field
+    GetStatic(FieldRef),
+    /// Pop into a static field
+    PutStatic(FieldRef),
+    /// Invoke a static method
+    InvokeStatic(MethodRef),
+    /// Invoke an instance method
+    InvokeVirtual(MethodRef),
+    /// Invoke a constructor or superclass method
+    InvokeSpecial(MethodRef),
+    /// Create a new object of the given class
+    New(String),
+    /// Create a new array with elements of the given type ([FieldType::Int] or
+    /// [FieldType::Char]), popping its length
+    NewArray(FieldType),
+    /// Push an element of an int array
+    IALoad,
+    /// Pop into an element of an int array
+    IAStore,
+    /// Push an element of a char array
+    CALoad,
+    /// Pop into an element of a char array
+    CAStore,
+}
+
+impl Instruction {
+    /// Number of values the instruction pops from and pushes to the operand stack
+    pub fn stack_effect(&self) -> (usize, usize) {
+        match self {
+            Instruction::Label(_)
+            | Instruction::Line(_)
+            | Instruction::Goto(_)
+            | Instruction::Return => (0, 0),
+            Instruction::IConst(_)
+            | Instruction::Ldc(_)
+            | Instruction::ILoad(_)
+            | Instruction::ALoad(_)
+            | Instruction::GetStatic(_)
+            | Instruction::New(_) => (0, 1),
+            Instruction::IStore(_)
+            | Instruction::AStore(_)
+            | Instruction::Pop
+            | Instruction::If(_, _)
+            | Instruction::TableSwitch(_, _, _)
+            | Instruction::LookupSwitch(_, _)
+            | Instruction::IReturn
+            | Instruction::PutStatic(_) => (1, 0),
+            Instruction::IAdd
+            | Instruction::ISub
+            | Instruction::IMul
+            | Instruction::IDiv
+            | Instruction::IRem
+            | Instruction::IAnd
+            | Instruction::IOr
+            | Instruction::IXor
+            | Instruction::IShl
+            | Instruction::IShr
+            | Instruction::IALoad
+            | Instruction::CALoad => (2, 1),
+            Instruction::INeg | Instruction::I2C | Instruction::NewArray(_) => (1, 1),
+            Instruction::Dup => (1, 2),
+            Instruction::DupX2 => (3, 4),
+            Instruction::IAStore | Instruction::CAStore => (3, 0),
+            Instruction::IfICmp(_, _) => (2, 0),
+            Instruction::InvokeStatic(m) => invoke_effect(&m.descriptor, 0),
+            Instruction::InvokeVirtual(m) | Instruction::InvokeSpecial(m) => {
+                invoke_effect(&m.descriptor, 1)
+            }
+        }
+    }
+
+    /// The labels this instruction may jump to
+    pub fn jump_targets(&self) -> Vec<Label> {
+        match self {
+            Instruction::If(_, l) | Instruction::IfICmp(_, l) | Instruction::Goto(l) => vec![*l],
+            Instruction::TableSwitch(_, labels, default) => {
+                labels.iter().chain([default]).copied().collect()
+            }
+            Instruction::LookupSwitch(pairs, default) => pairs
+                .iter()
+                .map(|(_, l)| l)
+                .chain([default])
+                .copied()
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether execution may continue with the next instruction
+    pub fn falls_through(&self) -> bool {
+        !matches!(
+            self,
+            Instruction::Goto(_)
+                | Instruction::TableSwitch(_, _, _)
+                | Instruction::LookupSwitch(_, _)
+                | Instruction::IReturn
+                | Instruction::Return
+        )
+    }
+
+    /// The local variable slot this instruction accesses, if any
+    pub fn local(&self) -> Option<usize> {
+        match self {
+            Instruction::ILoad(i)
+            | Instruction::IStore(i)
+            | Instruction::ALoad(i)
+            | Instruction::AStore(i) => Some(*i),
+            _ => None,
+        }
+    }
+}
+
+/// Stack effect of invoking a method, where `receivers` is 1 for instance methods
+/// and 0 for static methods (every type used takes up a single slot)
+fn invoke_effect(descriptor: &MethodDescriptor, receivers: usize) -> (usize, usize) {
+    (
+        descriptor.params.len() + receivers,
+        usize::from(descriptor.ret.is_some()),
+    )
+}
+
+/// A static field of a class
+#[derive(Debug, Clone)]
+pub struct Field {
+    /// name of the field
+    pub name: String,
+    /// type of the field
+    pub field_type: FieldType,
+}
+
+/// A method of a class, along with its code
+#[derive(Debug, Clone)]
+pub struct Method {
+    /// whether the method is public
+    pub public: bool,
+    /// whether the method is static
+    pub is_static: bool,
+    /// name of the method
+    pub name: String,
+    /// parameter and return types of the method
+    pub descriptor: MethodDescriptor,
+    /// instructions making up the method body
+    pub code: Vec<Instruction>,
+    /// maximum depth of the operand stack
+    pub max_stack: usize,
+    /// number of local variable slots, including parameters
+    pub max_locals: usize,
+}
+
+impl Method {
+    /// Construct a method, computing its stack and local variable limits
+    ///
+    /// # Panics
+    ///
+    /// Panics if a label is jumped to but never placed, or placed more than once,
+    /// since this means the code generator is broken.
+    pub fn new(
+        public: bool,
+        is_static: bool,
+        name: &str,
+        descriptor: MethodDescriptor,
+        code: Vec<Instruction>,
+    ) -> Self {
+        if let Err(label) = limits::check_labels(&code) {
+            panic!("label {} is malformed in method {}", label, name);
+        }
+
+        // instance methods take `this` as an implicit first parameter
+        let params = descriptor.params.len() + usize::from(!is_static);
+
+        Self {
+            public,
+            is_static,
+            name: name.to_owned(),
+            max_stack: limits::max_stack(&code),
+            max_locals: limits::max_locals(&code, params),
+            descriptor,
+            code,
+        }
+    }
+}
+
+/// A class, which is the unit of code generation
+#[derive(Debug, Clone)]
+pub struct Class {
+    /// name of the source file
+    pub source: String,
+    /// name of the class
+    pub name: String,
+    /// name of the super class
+    pub super_name: String,
+    /// static fields of the class
+    pub fields: Vec<Field>,
+    /// methods of the class
+    pub methods: Vec<Method>,
+}
@@ -0,0 +1,673 @@
+//! EGRE 591 part3 - Nathan Rowan and Trevin Vaughan
+//!
+//! Writing of classes in the binary class file format, so they can be run without Jasmin.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::{
+    frames::{frames, Frame, VerificationType},
+    Class, Condition, Constant, FieldRef, FieldType, Instruction, Label, Method, MethodRef,
+};
+use crate::code_gen::Error;
+
+/// Class file version 52.0 (Java 8), which requires a `StackMapTable` for methods with branches
+const VERSION: (u16, u16) = (0, 52);
+
+/// Access flag marking public classes and methods
+const ACC_PUBLIC: u16 = 0x0001;
+/// Access flag marking static fields and methods
+const ACC_STATIC: u16 = 0x0008;
+/// Access flag set on every modern class
+const ACC_SUPER: u16 = 0x0020;
+
+/// Entries of the constant pool
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum PoolEntry {
+    /// Text, encoded as modified UTF-8
+    Utf8(String),
+    /// An integer constant
+    Integer(i32),
+    /// A class, by the index of its name
+    Class(u16),
+    /// A string constant, by the index of its text
+    String(u16),
+    /// A field reference, by the indices of its class and name and type
+    Fieldref(u16, u16),
+    /// A method reference, by the indices of its class and name and type
+    Methodref(u16, u16),
+    /// A name and type, by the indices of the name and descriptor
+    NameAndType(u16, u16),
+}
+
+/// The constant pool of a class, where each entry appears once
+#[derive(Debug, Default)]
+struct ConstantPool {
+    /// entries in order, the first having index 1
+    entries: Vec<PoolEntry>,
+    /// index of each entry
+    indices: HashMap<PoolEntry, u16>,
+}
+
+impl ConstantPool {
+    /// Find or add an entry, returning its index
+    fn add(&mut self, entry: PoolEntry) -> Result<u16, Error> {
+        if let Some(index) = self.indices.get(&entry) {
+            return Ok(*index);
+        }
+
+        // indices start at 1 and the count (one more than the last index) must fit in a u16
+        let index = u16::try_from(self.entries.len() + 1)
+            .ok()
+            .filter(|i| *i < u16::MAX)
+            .ok_or_else(|| Error::ClassFileLimit(String::from("the constant pool")))?;
+
+        self.entries.push(entry.clone());
+        self.indices.insert(entry, index);
+
+        Ok(index)
+    }
+
+    /// Find or add some text
+    fn utf8(&mut self, text: &str) -> Result<u16, Error> {
+        self.add(PoolEntry::Utf8(text.to_owned()))
+    }
+
+    /// Find or add a class by its name
+    fn class(&mut self, name: &str) -> Result<u16, Error> {
+        let name = self.utf8(name)?;
+        self.add(PoolEntry::Class(name))
+    }
+
+    /// Find or add a name and type
+    fn name_and_type(&mut self, name: &str, descriptor: &str) -> Result<u16, Error> {
+        let name = self.utf8(name)?;
+        let descriptor = self.utf8(descriptor)?;
+        self.add(PoolEntry::NameAndType(name, descriptor))
+    }
+
+    /// Find or add a field reference
+    fn field_ref(&mut self, field: &FieldRef) -> Result<u16, Error> {
+        let class = self.class(&field.class)?;
+        let name_and_type = self.name_and_type(&field.name, &field.field_type.to_string())?;
+        self.add(PoolEntry::Fieldref(class, name_and_type))
+    }
+
+    /// Find or add a method reference
+    fn method_ref(&mut self, method: &MethodRef) -> Result<u16, Error> {
+        let class = self.class(&method.class)?;
+        let name_and_type = self.name_and_type(&method.name, &method.descriptor.to_string())?;
+        self.add(PoolEntry::Methodref(class, name_and_type))
+    }
+
+    /// Find or add a loadable constant
+    fn constant(&mut self, constant: &Constant) -> Result<u16, Error> {
+        match constant {
+            Constant::Int(n) => self.add(PoolEntry::Integer(*n)),
+            Constant::String(s) => {
+                let text = self.utf8(s)?;
+                self.add(PoolEntry::String(text))
+            }
+        }
+    }
+
+    /// Write the constant pool count followed by the entries
+    fn write(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        put_u16(out, self.entries.len() as u16 + 1);
+
+        for entry in &self.entries {
+            match entry {
+                PoolEntry::Utf8(text) => {
+                    let bytes = modified_utf8(text);
+                    let length = u16::try_from(bytes.len())
+                        .map_err(|_| Error::ClassFileLimit(String::from("a string constant")))?;
+                    out.push(1);
+                    put_u16(out, length);
+                    out.extend(bytes);
+                }
+                PoolEntry::Integer(n) => {
+                    out.push(3);
+                    out.extend(n.to_be_bytes());
+                }
+                PoolEntry::Class(name) => {
+                    out.push(7);
+                    put_u16(out, *name);
+                }
+                PoolEntry::String(text) => {
+                    out.push(8);
+                    put_u16(out, *text);
+                }
+                PoolEntry::Fieldref(class, name_and_type) => {
+                    out.push(9);
+                    put_u16(out, *class);
+                    put_u16(out, *name_and_type);
+                }
+                PoolEntry::Methodref(class, name_and_type) => {
+                    out.push(10);
+                    put_u16(out, *class);
+                    put_u16(out, *name_and_type);
+                }
+                PoolEntry::NameAndType(name, descriptor) => {
+                    out.push(12);
+                    put_u16(out, *name);
+                    put_u16(out, *descriptor);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Append a big-endian u16
+fn put_u16(out: &mut Vec<u8>, n: u16) {
+    out.extend(n.to_be_bytes());
+}
+
+/// Append a big-endian u32
+fn put_u32(out: &mut Vec<u8>, n: u32) {
+    out.extend(n.to_be_bytes());
+}
+
+/// Encode text the way the JVM stores it: like UTF-8, except that nul takes two bytes
+/// and characters outside of the basic multilingual plane are written as surrogate pairs
+fn modified_utf8(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+
+    for unit in text.encode_utf16() {
+        match unit {
+            0x0001..=0x007f => bytes.push(unit as u8),
+            0x0000 | 0x0080..=0x07ff => {
+                bytes.push(0xc0 | (unit >> 6) as u8);
+                bytes.push(0x80 | (unit & 0x3f) as u8);
+            }
+            _ => {
+                bytes.push(0xe0 | (unit >> 12) as u8);
+                bytes.push(0x80 | ((unit >> 6) & 0x3f) as u8);
+                bytes.push(0x80 | (unit & 0x3f) as u8);
+            }
+        }
+    }
+
+    bytes
+}
+
+/// Offset added to a condition to get its opcode, in the order the JVM defines them
+fn condition_offset(condition: Condition) -> u8 {
+    match condition {
+        Condition::Eq => 0,
+        Condition::Ne => 1,
+        Condition::Lt => 2,
+        Condition::Ge => 3,
+        Condition::Gt => 4,
+        Condition::Le => 5,
+    }
+}
+
+/// Code used by `newarray` for the type of the elements
+fn array_type(element: &FieldType) -> u8 {
+    match element {
+        FieldType::Char => 5,
+        FieldType::Int => 10,
+        _ => unreachable!("only arrays of ints or chars are made"),
+    }
+}
+
+/// Opcodes of the load and store instructions: the general form, and the first short form
+fn local_opcodes(instruction: &Instruction) -> Option<(u8, u8)> {
+    match instruction {
+        Instruction::ILoad(_) => Some((0x15, 0x1a)),
+        Instruction::ALoad(_) => Some((0x19, 0x2a)),
+        Instruction::IStore(_) => Some((0x36, 0x3b)),
+        Instruction::AStore(_) => Some((0x3a, 0x4b)),
+        _ => None,
+    }
+}
+
+/// Number of padding bytes after a switch opcode at `offset`, which align its operands to 4 bytes
+fn switch_padding(offset: usize) -> usize {
+    3 - offset % 4
+}
+
+/// Number of bytes an instruction at `offset` takes up, given the constant pool index it
+/// uses (if any)
+fn size(instruction: &Instruction, index: u16, offset: usize) -> usize {
+    match instruction {
+        Instruction::Label(_) | Instruction::Line(_) => 0,
+        Instruction::IConst(-1..=5) => 1,
+        Instruction::IConst(n) if i8::try_from(*n).is_ok() => 2,
+        Instruction::IConst(n) if i16::try_from(*n).is_ok() => 3,
+        Instruction::IConst(_) | Instruction::Ldc(_) if index <= u8::MAX as u16 => 2,
+        Instruction::IConst(_) | Instruction::Ldc(_) => 3,
+        Instruction::ILoad(slot)
+        | Instruction::IStore(slot)
+        | Instruction::ALoad(slot)
+        | Instruction::AStore(slot) => match slot {
+            0..=3 => 1,
+            4..=0xff => 2,
+            // wide
+            _ => 4,
+        },
+        Instruction::IAdd
+        | Instruction::ISub
+        | Instruction::IMul
+        | Instruction::IDiv
+        | Instruction::IRem
+        | Instruction::INeg
+        | Instruction::IAnd
+        | Instruction::IOr
+        | Instruction::IXor
+        | Instruction::IShl
+        | Instruction::IShr
+        | Instruction::I2C
+        | Instruction::Dup
+        | Instruction::DupX2
+        | Instruction::Pop
+        | Instruction::IReturn
+        | Instruction::Return
+        | Instruction::IALoad
+        | Instruction::IAStore
+        | Instruction::CALoad
+        | Instruction::CAStore => 1,
+        Instruction::NewArray(_) => 2,
+        Instruction::If(_, _)
+        | Instruction::IfICmp(_, _)
+        | Instruction::Goto(_)
+        | Instruction::GetStatic(_)
+        | Instruction::PutStatic(_)
+        | Instruction::InvokeStatic(_)
+        | Instruction::InvokeVirtual(_)
+        | Instruction::InvokeSpecial(_)
+        | Instruction::New(_) => 3,
+        // default, low and high, then each jump
+        Instruction::TableSwitch(_, labels, _) => {
+            1 + switch_padding(offset) + 12 + 4 * labels.len()
+        }
+        // default and count, then each value and jump
+        Instruction::LookupSwitch(pairs, _) => 1 + switch_padding(offset) + 8 + 8 * pairs.len(),
+    }
+}
+
+/// Add the constant pool entry an instruction uses (if any), returning its index or 0
+fn pool_index(instruction: &Instruction, pool: &mut ConstantPool) -> Result<u16, Error> {
+    match instruction {
+        Instruction::IConst(n) if i16::try_from(*n).is_err() => pool.constant(&Constant::Int(*n)),
+        Instruction::Ldc(constant) => pool.constant(constant),
+        Instruction::GetStatic(field) | Instruction::PutStatic(field) => pool.field_ref(field),
+        Instruction::InvokeStatic(method)
+        | Instruction::InvokeVirtual(method)
+        | Instruction::InvokeSpecial(method) => pool.method_ref(method),
+        Instruction::New(class) => pool.class(class),
+        _ => Ok(0),
+    }
+}
+
+/// Append the bytecode of an instruction at `offset`, given its constant pool index (if any).
+///
+/// Returns [None] if a jump doesn't fit in a 16 bit offset.
+fn encode(
+    out: &mut Vec<u8>,
+    instruction: &Instruction,
+    index: u16,
+    offset: usize,
+    labels: &HashMap<Label, usize>,
+) -> Option<()> {
+    // jumps are relative to the start of the instruction
+    let jump = |label: &Label| i16::try_from(labels[label] as isize - offset as isize).ok();
+    // switches have room for any jump within a method
+    let wide_jump = |label: &Label| (labels[label] as i32 - offset as i32).to_be_bytes();
+
+    match instruction {
+        Instruction::Label(_) | Instruction::Line(_) => (),
+        Instruction::IConst(n @ -1..=5) => out.push((0x03 + n) as u8),
+        Instruction::IConst(n) if i8::try_from(*n).is_ok() => {
+            out.push(0x10);
+            out.push(*n as u8);
+        }
+        Instruction::IConst(n) if i16::try_from(*n).is_ok() => {
+            out.push(0x11);
+            out.extend((*n as i16).to_be_bytes());
+        }
+        Instruction::IConst(_) | Instruction::Ldc(_) => {
+            if let Ok(index) = u8::try_from(index) {
+                out.push(0x12);
+                out.push(index);
+            } else {
+                out.push(0x13);
+                put_u16(out, index);
+            }
+        }
+        Instruction::ILoad(slot)
+        | Instruction::IStore(slot)
+        | Instruction::ALoad(slot)
+        | Instruction::AStore(slot) => {
+            let (opcode, short_opcode) = local_opcodes(instruction).unwrap();
+
+            match slot {
+                0..=3 => out.push(short_opcode + *slot as u8),
+                4..=0xff => {
+                    out.push(opcode);
+                    out.push(*slot as u8);
+                }
+                _ => {
+                    // wide
+                    out.push(0xc4);
+                    out.push(opcode);
+                    put_u16(out, *slot as u16);
+                }
+            }
+        }
+        Instruction::IAdd => out.push(0x60),
+        Instruction::ISub => out.push(0x64),
+        Instruction::IMul => out.push(0x68),
+        Instruction::IDiv => out.push(0x6c),
+        Instruction::IRem => out.push(0x70),
+        Instruction::INeg => out.push(0x74),
+        Instruction::IAnd => out.push(0x7e),
+        Instruction::IOr => out.push(0x80),
+        Instruction::IXor => out.push(0x82),
+        Instruction::IShl => out.push(0x78),
+        Instruction::IShr => out.push(0x7a),
+        Instruction::I2C => out.push(0x92),
+        Instruction::Dup => out.push(0x59),
+        Instruction::DupX2 => out.push(0x5b),
+        Instruction::IALoad => out.push(0x2e),
+        Instruction::CALoad => out.push(0x34),
+        Instruction::IAStore => out.push(0x4f),
+        Instruction::CAStore => out.push(0x55),
+        Instruction::NewArray(element) => {
+            out.push(0xbc);
+            out.push(array_type(element));
+        }
+        Instruction::Pop => out.push(0x57),
+        Instruction::If(condition, label) => {
+            out.push(0x99 + condition_offset(*condition));
+            out.extend(jump(label)?.to_be_bytes());
+        }
+        Instruction::IfICmp(condition, label) => {
+            out.push(0x9f + condition_offset(*condition));
+            out.extend(jump(label)?.to_be_bytes());
+        }
+        Instruction::Goto(label) => {
+            out.push(0xa7);
+            out.extend(jump(label)?.to_be_bytes());
+        }
+        Instruction::TableSwitch(low, targets, default) => {
+            out.push(0xaa);
+            out.extend(vec![0; switch_padding(offset)]);
+            out.extend(wide_jump(default));
+            out.extend(low.to_be_bytes());
+            out.extend((low + targets.len() as i32 - 1).to_be_bytes());
+            for label in targets {
+                out.extend(wide_jump(label));
+            }
+        }
+        Instruction::LookupSwitch(pairs, default) => {
+            out.push(0xab);
+            out.extend(vec![0; switch_padding(offset)]);
+            out.extend(wide_jump(default));
+            out.extend((pairs.len() as i32).to_be_bytes());
+            for (value, label) in pairs {
+                out.extend(value.to_be_bytes());
+                out.extend(wide_jump(label));
+            }
+        }
+        Instruction::IReturn => out.push(0xac),
+        Instruction::Return => out.push(0xb1),
+        Instruction::GetStatic(_)
+        | Instruction::PutStatic(_)
+        | Instruction::InvokeStatic(_)
+        | Instruction::InvokeVirtual(_)
+        | Instruction::InvokeSpecial(_)
+        | Instruction::New(_) => {
+            out.push(match instruction {
+                Instruction::GetStatic(_) => 0xb2,
+                Instruction::PutStatic(_) => 0xb3,
+                Instruction::InvokeVirtual(_) => 0xb6,
+                Instruction::InvokeSpecial(_) => 0xb7,
+                Instruction::InvokeStatic(_) => 0xb8,
+                _ => 0xbb,
+            });
+            put_u16(out, index);
+        }
+    }
+
+    Some(())
+}
+
+/// Append a verification type to a stack map frame
+fn write_type(
+    out: &mut Vec<u8>,
+    t: &VerificationType,
+    pool: &mut ConstantPool,
+    offsets: &[usize],
+) -> Result<(), Error> {
+    match t {
+        VerificationType::Top => out.push(0),
+        VerificationType::Integer => out.push(1),
+        VerificationType::UninitializedThis => out.push(6),
+        VerificationType::Object(class) => {
+            out.push(7);
+            put_u16(out, pool.class(class)?);
+        }
+        VerificationType::Uninitialized(i) => {
+            out.push(8);
+            put_u16(out, offsets[*i] as u16);
+        }
+    }
+
+    Ok(())
+}
+
+/// Build the `StackMapTable` attribute body from the frames at each offset, using full frames
+fn stack_map_table(
+    table: &BTreeMap<usize, &Frame>,
+    pool: &mut ConstantPool,
+    offsets: &[usize],
+) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::new();
+    put_u16(&mut out, table.len() as u16);
+
+    let mut previous = None;
+    for (offset, frame) in table {
+        // each offset is stored relative to the previous frame
+        let delta = match previous {
+            Some(previous) => offset - previous - 1,
+            None => *offset,
+        };
+        previous = Some(*offset);
+
+        // trailing unusable locals can be left out
+        let locals = match frame
+            .locals
+            .iter()
+            .rposition(|t| *t != VerificationType::Top)
+        {
+            Some(last) => &frame.locals[..=last],
+            None => &[],
+        };
+
+        out.push(255);
+        put_u16(&mut out, delta as u16);
+        put_u16(&mut out, locals.len() as u16);
+        for t in locals {
+            write_type(&mut out, t, pool, offsets)?;
+        }
+        put_u16(&mut out, frame.stack.len() as u16);
+        for t in &frame.stack {
+            write_type(&mut out, t, pool, offsets)?;
+        }
+    }
+
+    Ok(out)
+}
+
+/// Build the `Code` attribute body of a method
+fn code_attribute(
+    method: &Method,
+    class_name: &str,
+    pool: &mut ConstantPool,
+) -> Result<Vec<u8>, Error> {
+    let too_large = || Error::ClassFileLimit(format!("method {}", method.name));
+
+    // every local slot and stack size below fits once these do
+    let max_stack = u16::try_from(method.max_stack).map_err(|_| too_large())?;
+    let max_locals = u16::try_from(method.max_locals).map_err(|_| too_large())?;
+
+    // unreachable code is left out, since the verifier would need frames for it
+    let frames = frames(method, class_name);
+    let reachable: Vec<usize> = (0..method.code.len())
+        .filter(|i| frames[*i].is_some())
+        .collect();
+
+    // constant pool indices come first, since they decide the size of some instructions
+    let mut indices = vec![0; method.code.len()];
+    for i in &reachable {
+        indices[*i] = pool_index(&method.code[*i], pool)?;
+    }
+
+    // then the offset of each instruction (labels included)
+    let mut offsets = vec![0; method.code.len()];
+    let mut labels = HashMap::new();
+    let mut length = 0;
+    for i in &reachable {
+        offsets[*i] = length;
+        if let Instruction::Label(label) = &method.code[*i] {
+            labels.insert(*label, length);
+        }
+        length += size(&method.code[*i], indices[*i], length);
+    }
+
+    let mut bytecode = Vec::new();
+    for i in &reachable {
+        encode(
+            &mut bytecode,
+            &method.code[*i],
+            indices[*i],
+            offsets[*i],
+            &labels,
+        )
+        .ok_or_else(too_large)?;
+    }
+
+    if bytecode.len() > u16::MAX as usize {
+        return Err(too_large());
+    }
+
+    // every jump target needs a frame
+    let targets: HashSet<Label> = reachable
+        .iter()
+        .flat_map(|i| method.code[*i].jump_targets())
+        .collect();
+    let table: BTreeMap<usize, &Frame> = reachable
+        .iter()
+        .filter(|i| matches!(&method.code[**i], Instruction::Label(l) if targets.contains(l)))
+        .map(|i| (offsets[*i], frames[*i].as_ref().unwrap()))
+        .collect();
+
+    // source lines which don't fit in the table are left out
+    let lines: Vec<(usize, u16)> = reachable
+        .iter()
+        .filter_map(|i| match &method.code[*i] {
+            Instruction::Line(line) => Some((offsets[*i], u16::try_from(*line).ok()?)),
+            _ => None,
+        })
+        .collect();
+
+    let mut out = Vec::new();
+    put_u16(&mut out, max_stack);
+    put_u16(&mut out, max_locals);
+    put_u32(&mut out, bytecode.len() as u32);
+    out.extend(bytecode);
+    // no exception handlers
+    put_u16(&mut out, 0);
+
+    let attributes = usize::from(!table.is_empty()) + usize::from(!lines.is_empty());
+    put_u16(&mut out, attributes as u16);
+
+    if !table.is_empty() {
+        let stack_map = stack_map_table(&table, pool, &offsets)?;
+        put_u16(&mut out, pool.utf8("StackMapTable")?);
+        put_u32(&mut out, stack_map.len() as u32);
+        out.extend(stack_map);
+    }
+
+    if !lines.is_empty() {
+        put_u16(&mut out, pool.utf8("LineNumberTable")?);
+        put_u32(&mut out, 2 + 4 * lines.len() as u32);
+        put_u16(&mut out, lines.len() as u16);
+        for (offset, line) in lines {
+            put_u16(&mut out, offset as u16);
+            put_u16(&mut out, line);
+        }
+    }
+
+    Ok(out)
+}
+
+impl Class {
+    /// Write the class in the binary class file format
+    ///
+    /// # Errors
+    ///
+    /// Fails if the class is too large for the format, see [Error::ClassFileLimit].
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut pool = ConstantPool::default();
+        // everything after the constant pool, which is written last since it grows along the way
+        let mut body = Vec::new();
+
+        put_u16(&mut body, ACC_SUPER);
+        put_u16(&mut body, pool.class(&self.name)?);
+        put_u16(&mut body, pool.class(&self.super_name)?);
+        // no interfaces
+        put_u16(&mut body, 0);
+
+        let fields = u16::try_from(self.fields.len())
+            .map_err(|_| Error::ClassFileLimit(String::from("the number of fields")))?;
+        put_u16(&mut body, fields);
+        for field in &self.fields {
+            put_u16(&mut body, ACC_STATIC);
+            put_u16(&mut body, pool.utf8(&field.name)?);
+            put_u16(&mut body, pool.utf8(&field.field_type.to_string())?);
+            // no attributes
+            put_u16(&mut body, 0);
+        }
+
+        let methods = u16::try_from(self.methods.len())
+            .map_err(|_| Error::ClassFileLimit(String::from("the number of methods")))?;
+        put_u16(&mut body, methods);
+        for method in &self.methods {
+            let mut flags = 0;
+            if method.public {
+                flags |= ACC_PUBLIC;
+            }
+            if method.is_static {
+                flags |= ACC_STATIC;
+            }
+
+            put_u16(&mut body, flags);
+            put_u16(&mut body, pool.utf8(&method.name)?);
+            put_u16(&mut body, pool.utf8(&method.descriptor.to_string())?);
+
+            let code = code_attribute(method, &self.name, &mut pool)?;
+            put_u16(&mut body, 1);
+            put_u16(&mut body, pool.utf8("Code")?);
+            put_u32(&mut body, code.len() as u32);
+            body.extend(code);
+        }
+
+        // the source file attribute
+        put_u16(&mut body, 1);
+        put_u16(&mut body, pool.utf8("SourceFile")?);
+        put_u32(&mut body, 2);
+        put_u16(&mut body, pool.utf8(&self.source)?);
+
+        let mut out = Vec::new();
+        put_u32(&mut out, 0xcafe_babe);
+        put_u16(&mut out, VERSION.0);
+        put_u16(&mut out, VERSION.1);
+        pool.write(&mut out)?;
+        out.extend(body);
+
+        Ok(out)
+    }
+}
@@ -0,0 +1,231 @@
+//! EGRE 591 part3 - Nathan Rowan and Trevin Vaughan
+//!
+//! Type inference over method bodies, giving the local variable and operand stack types
+//! before each instruction. These are the frames of the `StackMapTable` attribute.
+
+use super::{limits::label_positions, Constant, FieldType, Instruction, Method};
+
+/// Types of values as seen by the bytecode verifier
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationType {
+    /// Unusable value, such as an unassigned local variable
+    Top,
+    /// An integer
+    Integer,
+    /// An instance of the given class (or array descriptor)
+    Object(String),
+    /// `this` inside of a constructor, before the super constructor is called
+    UninitializedThis,
+    /// An object created by the `new` instruction at the given index, before its constructor is called
+    Uninitialized(usize),
+}
+
+impl From<&FieldType> for VerificationType {
+    fn from(field_type: &FieldType) -> Self {
+        match field_type {
+            // chars are ints on the operand stack
+            FieldType::Int | FieldType::Char => VerificationType::Integer,
+            FieldType::Object(class) => VerificationType::Object(class.to_owned()),
+            // arrays are named by their descriptor
+            FieldType::Array(_) => VerificationType::Object(field_type.to_string()),
+        }
+    }
+}
+
+/// Types of the local variables and operand stack at some point in a method
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// types of the local variables, indexed by slot
+    pub locals: Vec<VerificationType>,
+    /// types of the values on the operand stack, with the top last
+    pub stack: Vec<VerificationType>,
+}
+
+impl Frame {
+    /// Combine the frames of two paths meeting at the same instruction.
+    ///
+    /// Values which differ between the paths can't be used afterwards.
+    fn merge(&self, other: &Frame) -> Frame {
+        let merge = |a: &[VerificationType], b: &[VerificationType]| {
+            a.iter()
+                .zip(b)
+                .map(|(a, b)| {
+                    if a == b {
+                        a.clone()
+                    } else {
+                        VerificationType::Top
+                    }
+                })
+                .collect()
+        };
+
+        Frame {
+            locals: merge(&self.locals, &other.locals),
+            stack: merge(&self.stack, &other.stack),
+        }
+    }
+
+    /// Pop `n` values off of the operand stack
+    fn pop(&mut self, n: usize) {
+        self.stack.truncate(self.stack.len() - n);
+    }
+
+    /// The frame after executing the instruction at index `i`
+    fn step(&self, instruction: &Instruction, i: usize, class_name: &str) -> Frame {
+        let mut frame = self.clone();
+
+        match instruction {
+            Instruction::Label(_)
+            | Instruction::Line(_)
+            | Instruction::Goto(_)
+            | Instruction::Return
+            | Instruction::INeg
+            | Instruction::I2C => (),
+            Instruction::IConst(_) | Instruction::ILoad(_) => {
+                frame.stack.push(VerificationType::Integer)
+            }
+            Instruction::Ldc(constant) => frame.stack.push(match constant {
+                Constant::Int(_) => VerificationType::Integer,
+                Constant::String(_) => (&FieldType::string()).into(),
+            }),
+            Instruction::ALoad(slot) => frame.stack.push(frame.locals[*slot].clone()),
+            Instruction::IStore(slot) => {
+                frame.pop(1);
+                frame.locals[*slot] = VerificationType::Integer;
+            }
+            Instruction::AStore(slot) => {
+                frame.locals[*slot] = frame.stack.pop().unwrap();
+            }
+            Instruction::IAdd
+            | Instruction::ISub
+            | Instruction::IMul
+            | Instruction::IDiv
+            | Instruction::IRem
+            | Instruction::IAnd
+            | Instruction::IOr
+            | Instruction::IXor
+            | Instruction::IShl
+            | Instruction::IShr
+            | Instruction::IALoad
+            | Instruction::CALoad => {
+                frame.pop(2);
+                frame.stack.push(VerificationType::Integer);
+            }
+            Instruction::IAStore | Instruction::CAStore => frame.pop(3),
+            Instruction::IfICmp(_, _) => frame.pop(2),
+            Instruction::Dup => frame.stack.push(frame.stack.last().unwrap().clone()),
+            Instruction::DupX2 => {
+                let top = frame.stack.last().unwrap().clone();
+                frame.stack.insert(frame.stack.len() - 3, top);
+            }
+            Instruction::Pop
+            | Instruction::If(_, _)
+            | Instruction::TableSwitch(_, _, _)
+            | Instruction::LookupSwitch(_, _)
+            | Instruction::IReturn
+            | Instruction::PutStatic(_) => frame.pop(1),
+            Instruction::GetStatic(field) => frame.stack.push((&field.field_type).into()),
+            Instruction::InvokeStatic(m)
+            | Instruction::InvokeVirtual(m)
+            | Instruction::InvokeSpecial(m) => {
+                frame.pop(m.descriptor.params.len());
+
+                if !matches!(instruction, Instruction::InvokeStatic(_)) {
+                    let receiver = frame.stack.pop().unwrap();
+
+                    // constructors initialize every copy of the receiver
+                    if m.name == "<init>" {
+                        let initialized = match receiver {
+                            VerificationType::UninitializedThis => {
+                                VerificationType::Object(class_name.to_owned())
+                            }
+                            _ => VerificationType::Object(m.class.clone()),
+                        };
+
+                        for t in frame.locals.iter_mut().chain(frame.stack.iter_mut()) {
+                            if *t == receiver {
+                                *t = initialized.clone();
+                            }
+                        }
+                    }
+                }
+
+                if let Some(ret) = &m.descriptor.ret {
+                    frame.stack.push(ret.into());
+                }
+            }
+            Instruction::New(_) => frame.stack.push(VerificationType::Uninitialized(i)),
+            Instruction::NewArray(element) => {
+                frame.pop(1);
+                frame
+                    .stack
+                    .push((&FieldType::Array(Box::new(element.clone()))).into());
+            }
+        }
+
+        frame
+    }
+}
+
+/// Computes the frame before each instruction of a method belonging to `class_name`.
+///
+/// Instructions which can never be reached have no frame.
+pub fn frames(method: &Method, class_name: &str) -> Vec<Option<Frame>> {
+    let code = &method.code;
+    let labels = label_positions(code);
+
+    // parameters are the first local variables, after `this` for instance methods
+    let mut locals = Vec::new();
+    if !method.is_static {
+        locals.push(if method.name == "<init>" {
+            VerificationType::UninitializedThis
+        } else {
+            VerificationType::Object(class_name.to_owned())
+        });
+    }
+    locals.extend(method.descriptor.params.iter().map(VerificationType::from));
+    locals.resize(method.max_locals.max(locals.len()), VerificationType::Top);
+
+    let mut frames: Vec<Option<Frame>> = vec![None; code.len()];
+    let mut work = Vec::new();
+
+    if !code.is_empty() {
+        frames[0] = Some(Frame {
+            locals,
+            stack: Vec::new(),
+        });
+        work.push(0);
+    }
+
+    // propagate frames until nothing changes, which happens since merging only ever adds tops
+    while let Some(pc) = work.pop() {
+        let instruction = &code[pc];
+        let after = frames[pc]
+            .as_ref()
+            .unwrap()
+            .step(instruction, pc, class_name);
+
+        let mut successors: Vec<usize> = instruction
+            .jump_targets()
+            .iter()
+            .map(|label| labels[label])
+            .collect();
+        if instruction.falls_through() && pc + 1 < code.len() {
+            successors.push(pc + 1);
+        }
+
+        for next in successors {
+            let merged = match &frames[next] {
+                Some(existing) => existing.merge(&after),
+                None => after.clone(),
+            };
+
+            if frames[next].as_ref() != Some(&merged) {
+                frames[next] = Some(merged);
+                work.push(next);
+            }
+        }
+    }
+
+    frames
+}
@@ -0,0 +1,83 @@
+//! EGRE 591 part3 - Nathan Rowan and Trevin Vaughan
+//!
+//! Analysis of method bodies: the `max_stack` and `max_locals` of a method,
+//! and checking that every label is placed exactly once.
+
+use std::collections::{HashMap, HashSet};
+
+use super::{Instruction, Label};
+
+/// Finds the position of every label in a method body
+pub fn label_positions(code: &[Instruction]) -> HashMap<Label, usize> {
+    code.iter()
+        .enumerate()
+        .filter_map(|(i, instruction)| match instruction {
+            Instruction::Label(label) => Some((*label, i)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Checks that each label is placed exactly once, and that every jump targets a placed label.
+///
+/// Returns the first offending label.
+pub fn check_labels(code: &[Instruction]) -> Result<(), Label> {
+    let mut placed = HashSet::new();
+
+    for instruction in code {
+        if let Instruction::Label(label) = instruction {
+            if !placed.insert(*label) {
+                return Err(*label);
+            }
+        }
+    }
+
+    for instruction in code {
+        for label in instruction.jump_targets() {
+            if !placed.contains(&label) {
+                return Err(label);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Computes the maximum depth of the operand stack over every path through a method body
+pub fn max_stack(code: &[Instruction]) -> usize {
+    let labels = label_positions(code);
+
+    // depth of the stack before each instruction, once it has been reached
+    let mut depths: Vec<Option<usize>> = vec![None; code.len()];
+    let mut work = vec![(0, 0)];
+    let mut max = 0;
+
+    while let Some((pc, depth)) = work.pop() {
+        if pc >= code.len() || depths[pc].is_some() {
+            continue;
+        }
+        depths[pc] = Some(depth);
+
+        let instruction = &code[pc];
+
+        let (pops, pushes) = instruction.stack_effect();
+        let depth = depth - pops + pushes;
+        max = max.max(depth);
+
+        for label in instruction.jump_targets() {
+            work.push((labels[&label], depth));
+        }
+        if instruction.falls_through() {
+            work.push((pc + 1, depth));
+        }
+    }
+
+    max
+}
+
+/// Computes the number of local variable slots used by a method body taking `params` parameters
+pub fn max_locals(code: &[Instruction], params: usize) -> usize {
+    code.iter()
+        .filter_map(|i| i.local().map(|i| i + 1))
+        .fold(params, usize::max)
+}
@@ -0,0 +1,210 @@
+//! EGRE 591 part3 - Nathan Rowan and Trevin Vaughan
+//!
+//! Printing of classes as Jasmin assembly.
+
+use std::fmt::Display;
+
+use super::*;
+
+/// Indentation of instructions inside of a method
+const INDENT: &str = "    ";
+
+/// Creates a separator for jvm instructions such as `astore_1`
+fn sep(offset: usize) -> char {
+    if offset < 4 {
+        '_'
+    } else {
+        ' '
+    }
+}
+
+impl Display for Label {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Label_{}", self.0)
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Condition::Eq => "eq",
+            Condition::Ne => "ne",
+            Condition::Lt => "lt",
+            Condition::Ge => "ge",
+            Condition::Gt => "gt",
+            Condition::Le => "le",
+        };
+
+        write!(f, "{}", str)
+    }
+}
+
+impl Display for Constant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Constant::Int(n) => write!(f, "{}", n),
+            Constant::String(s) => write!(f, "\"{}\"", jasmin_escape(s)),
+        }
+    }
+}
+
+/// Escapes a string for Jasmin, which only reads printable ASCII in string constants
+fn jasmin_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped += "\\\"",
+            '\\' => escaped += "\\\\",
+            '\n' => escaped += "\\n",
+            '\t' => escaped += "\\t",
+            ' '..='~' => escaped.push(c),
+            // characters outside the basic plane are written as a surrogate pair
+            _ => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    escaped += &format!("\\u{:04x}", unit);
+                }
+            }
+        }
+    }
+    escaped
+}
+
+impl Display for FieldRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{} {}", self.class, self.name, self.field_type)
+    }
+}
+
+impl Display for MethodRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}{}", self.class, self.name, self.descriptor)
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // labels are not indented
+        if let Instruction::Label(label) = self {
+            return write!(f, "{}:", label);
+        }
+        // line numbers are a directive
+        if let Instruction::Line(line) = self {
+            return write!(f, "{}.line {}", INDENT, line);
+        }
+
+        write!(f, "{}", INDENT)?;
+
+        match self {
+            Instruction::Label(_) | Instruction::Line(_) => unreachable!(),
+            // use the shortest form of integer constants
+            Instruction::IConst(-1) => write!(f, "iconst_m1"),
+            Instruction::IConst(n @ 0..=5) => write!(f, "iconst_{}", n),
+            Instruction::IConst(n) if i8::try_from(*n).is_ok() => write!(f, "bipush {}", n),
+            Instruction::IConst(n) if i16::try_from(*n).is_ok() => write!(f, "sipush {}", n),
+            Instruction::IConst(n) => write!(f, "ldc {}", n),
+            Instruction::Ldc(c) => write!(f, "ldc {}", c),
+            Instruction::ILoad(i) => write!(f, "iload{}{}", sep(*i), i),
+            Instruction::IStore(i) => write!(f, "istore{}{}", sep(*i), i),
+            Instruction::ALoad(i) => write!(f, "aload{}{}", sep(*i), i),
+            Instruction::AStore(i) => write!(f, "astore{}{}", sep(*i), i),
+            Instruction::IAdd => write!(f, "iadd"),
+            Instruction::ISub => write!(f, "isub"),
+            Instruction::IMul => write!(f, "imul"),
+            Instruction::IDiv => write!(f, "idiv"),
+            Instruction::IRem => write!(f, "irem"),
+            Instruction::INeg => write!(f, "ineg"),
+            Instruction::IAnd => write!(f, "iand"),
+            Instruction::IOr => write!(f, "ior"),
+            Instruction::IXor => write!(f, "ixor"),
+            Instruction::IShl => write!(f, "ishl"),
+            Instruction::IShr => write!(f, "ishr"),
+            Instruction::I2C => write!(f, "i2c"),
+            Instruction::Dup => write!(f, "dup"),
+            Instruction::DupX2 => write!(f, "dup_x2"),
+            Instruction::Pop => write!(f, "pop"),
+            Instruction::If(c, l) => write!(f, "if{} {}", c, l),
+            Instruction::IfICmp(c, l) => write!(f, "if_icmp{} {}", c, l),
+            Instruction::Goto(l) => write!(f, "goto {}", l),
+            Instruction::TableSwitch(low, labels, default) => {
+                writeln!(f, "tableswitch {}", low)?;
+                for label in labels {
+                    writeln!(f, "{}{}{}", INDENT, INDENT, label)?;
+                }
+                write!(f, "{}{}default : {}", INDENT, INDENT, default)
+            }
+            Instruction::LookupSwitch(pairs, default) => {
+                writeln!(f, "lookupswitch")?;
+                for (value, label) in pairs {
+                    writeln!(f, "{}{}{} : {}", INDENT, INDENT, value, label)?;
+                }
+                write!(f, "{}{}default : {}", INDENT, INDENT, default)
+            }
+            Instruction::IReturn => write!(f, "ireturn"),
+            Instruction::Return => write!(f, "return"),
+            Instruction::GetStatic(field) => write!(f, "getstatic {}", field),
+            Instruction::PutStatic(field) => write!(f, "putstatic {}", field),
+            Instruction::InvokeStatic(m) => write!(f, "invokestatic {}", m),
+            Instruction::InvokeVirtual(m) => write!(f, "invokevirtual {}", m),
+            Instruction::InvokeSpecial(m) => write!(f, "invokespecial {}", m),
+            Instruction::New(class) => write!(f, "new {}", class),
+            Instruction::NewArray(FieldType::Int) => write!(f, "newarray int"),
+            Instruction::NewArray(FieldType::Char) => write!(f, "newarray char"),
+            Instruction::NewArray(_) => unreachable!("only arrays of ints or chars are made"),
+            Instruction::IALoad => write!(f, "iaload"),
+            Instruction::IAStore => write!(f, "iastore"),
+            Instruction::CALoad => write!(f, "caload"),
+            Instruction::CAStore => write!(f, "castore"),
+        }
+    }
+}
+
+impl Display for Method {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, ".method ")?;
+        if self.public {
+            write!(f, "public ")?;
+        }
+        if self.is_static {
+            write!(f, "static ")?;
+        }
+        writeln!(f, "{}{}", self.name, self.descriptor)?;
+
+        writeln!(f, "{}.limit stack {}", INDENT, self.max_stack)?;
+        writeln!(f, "{}.limit locals {}", INDENT, self.max_locals)?;
+
+        for instruction in &self.code {
+            writeln!(f, "{}", instruction)?;
+        }
+
+        writeln!(f, ".end method")
+    }
+}
+
+impl Display for Class {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // file headers
+        writeln!(
+            f,
+            "; created using EGRE-591 ToyC compiler by Nathan Rowan and Trevin Vaughan\n"
+        )?;
+
+        writeln!(f, ".source {}", self.source)?;
+        writeln!(f, ".class {}", self.name)?;
+        writeln!(f, ".super {}\n", self.super_name)?;
+
+        // fields must be declared before any methods
+        for field in &self.fields {
+            writeln!(f, ".field static {} {}", field.name, field.field_type)?;
+        }
+        if !self.fields.is_empty() {
+            writeln!(f)?;
+        }
+
+        for (i, method) in self.methods.iter().enumerate() {
+            writeln!(f, "; >> METHOD {} <<", i)?;
+            writeln!(f, "{}", method)?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,84 +1,58 @@
 //! EGRE 591 part3 - Nathan Rowan and Trevin Vaughan
 //!
-//! Contextual errors. Created using [crate::file_buffer::FileBuffer].
-//! [MaybeContext] allows mixing these errors with others.
+//! Contextual errors. Created using [crate::file_buffer::FileBuffer] or
+//! [crate::source::Source]. [MaybeContext] allows mixing these errors with others.
 
 use std::fmt::Display;
 
-use colored::Colorize;
+use crate::{
+    diagnostic::{Coded, Diagnostic, Location, Severity},
+    span::Span,
+};
 
 /// Gives locational context to the inner error/warning type
 #[derive(Debug)]
 pub struct Context<T: Display> {
     /// The type of error that occurred
     kind: T,
-    /// The contents of the line on which the error occurred
-    line: String,
-    /// The line number on which the error occurred
-    line_num: usize,
-    /// The place along the line on which the error occurred
-    line_index: usize,
-    /// The name of the file in which the error occurred
-    file_name: String,
+    /// Where the error occurred
+    ///
+    /// This is boxed so results carrying a context stay small
+    location: Box<Location>,
 }
 
 impl<T: Display> Context<T> {
     /// Construct a new `Error<T>`
-    pub fn new(
-        kind: T,
-        line: String,
-        line_num: usize,
-        line_index: usize,
-        file_name: String,
-    ) -> Self {
+    pub fn new(kind: T, line: String, span: Span, file_name: String) -> Self {
         Self {
             kind,
-            line,
-            line_num,
-            line_index,
-            file_name,
+            location: Box::new(Location {
+                file_name,
+                line,
+                span,
+            }),
         }
     }
 
+    /// The type of error that occurred
+    pub fn kind(&self) -> &T {
+        &self.kind
+    }
+
     /// Allows the conversion from one error type to another while keeping the context the same.
     pub fn map_kind<F: FnOnce(T) -> U, U: Display>(self, f: F) -> Context<U> {
-        let Self {
-            kind,
-            line,
-            line_num,
-            line_index,
-            file_name,
-        } = self;
-
-        let kind = f(kind);
-
         Context {
-            kind,
-            line,
-            line_num,
-            line_index,
-            file_name,
+            kind: f(self.kind),
+            location: self.location,
         }
     }
-}
-
-impl<T: Display> Display for Context<T> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // [ERROR] unclosed comment in test.c:12:34:
-        // 123 "hello" /*
-        //              ^~~ error happened here
 
-        write!(
-            f,
-            "{} in {}:{}:{}:\n{}\n{}{}\n",
-            self.kind,
-            self.file_name.purple(),
-            (self.line_num + 1).to_string().purple(),
-            (self.line_index + 1).to_string().purple(),
-            self.line,
-            " ".repeat(self.line_index),
-            "^~~ happened here".blue()
-        )
+    /// Turns the error into a diagnostic that can be reported
+    pub fn into_diagnostic(self, severity: Severity) -> Diagnostic
+    where
+        T: Coded,
+    {
+        Diagnostic::coded(severity, self.kind).at(*self.location)
     }
 }
 
@@ -98,6 +72,17 @@ impl<T: Display> MaybeContext<T> {
             MaybeContext::NoContext(e) => MaybeContext::NoContext(f(e)),
         }
     }
+
+    /// Turns the error into a diagnostic that can be reported
+    pub fn into_diagnostic(self, severity: Severity) -> Diagnostic
+    where
+        T: Coded,
+    {
+        match self {
+            MaybeContext::Context(e) => e.into_diagnostic(severity),
+            MaybeContext::NoContext(e) => Diagnostic::coded(severity, e),
+        }
+    }
 }
 
 impl<T: Display> From<Context<T>> for MaybeContext<T> {
@@ -111,12 +96,3 @@ impl<T: Display> From<T> for MaybeContext<T> {
         Self::NoContext(value)
     }
 }
-
-impl<T: Display> Display for MaybeContext<T> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            MaybeContext::Context(c) => c.fmt(f),
-            MaybeContext::NoContext(n) => n.fmt(f),
-        }
-    }
-}
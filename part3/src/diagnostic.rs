@@ -0,0 +1,248 @@
+//! EGRE 591 part3 - Nathan Rowan and Trevin Vaughan
+//!
+//! Diagnostics reported by every pass of the compiler. Errors and warnings are turned
+//! into a [Diagnostic] (see [crate::context::Context::into_diagnostic]) and handed to a
+//! [Sink], which decides how they are rendered.
+
+use std::{fmt::Display, io::Write};
+
+use colored::Colorize;
+
+use crate::span::{Position, Span};
+
+pub mod explain;
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The file cannot be compiled
+    Error,
+    /// The file can be compiled, but probably not as intended
+    Warning,
+}
+
+impl Severity {
+    /// Lower case name of the severity
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// Where in a source file a diagnostic happened
+#[derive(Debug, Clone)]
+pub struct Location {
+    /// The name of the file, as given on the command line
+    pub file_name: String,
+    /// The contents of the first line of the span
+    pub line: String,
+    /// The part of the file the diagnostic is about
+    pub span: Span,
+}
+
+/// A single error or warning, ready to be rendered
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// How serious the diagnostic is
+    pub severity: Severity,
+    /// Stable identifier for the kind of diagnostic, if it has one
+    pub code: Option<&'static str>,
+    /// What went wrong
+    pub message: String,
+    /// Where it went wrong, if it is known
+    pub location: Option<Location>,
+    /// Extra information shown after the message
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    /// Construct a diagnostic with no location, code or notes
+    pub fn new<T: Display>(severity: Severity, message: T) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.to_string(),
+            location: None,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Construct a diagnostic for an error or warning which has a code
+    pub fn coded<T: Display + Coded>(severity: Severity, kind: T) -> Self {
+        Self::new(severity, &kind).with_code(kind.code())
+    }
+
+    /// Gives the diagnostic a location
+    pub fn at(mut self, location: Location) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    /// Gives the diagnostic a code
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Adds a note to the end of the diagnostic
+    pub fn with_note<T: Display>(mut self, note: T) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+}
+
+/// Errors and warnings which have a stable code, see [explain]
+pub trait Coded {
+    /// The code, which never changes once it is given out
+    fn code(&self) -> &'static str;
+}
+
+/// Somewhere to send diagnostics
+pub trait Sink {
+    /// Reports a single diagnostic
+    fn emit(&mut self, diagnostic: &Diagnostic);
+}
+
+/// Renders diagnostics for people to read, pointing at the source with a caret
+pub struct Human<W: Write> {
+    /// Where the diagnostics are written
+    output: W,
+}
+
+impl<W: Write> Human<W> {
+    /// Construct a new renderer
+    pub fn new(output: W) -> Self {
+        Self { output }
+    }
+}
+
+impl<W: Write> Sink for Human<W> {
+    fn emit(&mut self, diagnostic: &Diagnostic) {
+        // [ERROR] unclosed comment in test.c:12:34:
+        // 123 "hello" /*
+        //             ^~~ happened here
+
+        let label = match diagnostic.code {
+            Some(code) => format!("[{} {}]", diagnostic.severity.as_str().to_uppercase(), code),
+            None => format!("[{}]", diagnostic.severity.as_str().to_uppercase()),
+        };
+        let label = match diagnostic.severity {
+            Severity::Error => label.red(),
+            Severity::Warning => label.yellow(),
+        };
+
+        let mut s = format!("{} {}", label, diagnostic.message);
+
+        if let Some(Location {
+            file_name,
+            line,
+            span,
+        }) = &diagnostic.location
+        {
+            s += &format!(
+                " in {}:{}:{}:\n{}\n{}{}\n",
+                file_name.purple(),
+                (span.start.line + 1).to_string().purple(),
+                (span.start.column + 1).to_string().purple(),
+                line,
+                " ".repeat(span.start.column),
+                "^~~ happened here".blue()
+            );
+        }
+
+        for note in &diagnostic.notes {
+            s += &format!("{} {}\n", "note:".blue(), note);
+        }
+
+        // there is nowhere left to report a failure to write a diagnostic
+        let _ = writeln!(self.output, "{}", s);
+    }
+}
+
+/// Renders diagnostics as JSON for other programs to read, one object per line
+pub struct Json<W: Write> {
+    /// Where the diagnostics are written
+    output: W,
+}
+
+impl<W: Write> Json<W> {
+    /// Construct a new renderer
+    pub fn new(output: W) -> Self {
+        Self { output }
+    }
+}
+
+impl<W: Write> Sink for Json<W> {
+    fn emit(&mut self, diagnostic: &Diagnostic) {
+        // {"severity":"error","code":null,"message":"...","file":"test.tc",
+        //  "span":{"start":{"line":1,"column":1},"end":{"line":1,"column":3}},"notes":[]}
+
+        let code = match diagnostic.code {
+            Some(code) => json_string(code),
+            None => "null".to_owned(),
+        };
+
+        let (file, span) = match &diagnostic.location {
+            Some(Location {
+                file_name, span, ..
+            }) => (
+                json_string(file_name),
+                format!(
+                    "{{\"start\":{},\"end\":{}}}",
+                    json_position(span.start),
+                    json_position(span.end)
+                ),
+            ),
+            None => ("null".to_owned(), "null".to_owned()),
+        };
+
+        let notes = diagnostic
+            .notes
+            .iter()
+            .map(|n| json_string(n))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        // there is nowhere left to report a failure to write a diagnostic
+        let _ = writeln!(
+            self.output,
+            "{{\"severity\":\"{}\",\"code\":{},\"message\":{},\"file\":{},\"span\":{},\"notes\":[{}]}}",
+            diagnostic.severity.as_str(),
+            code,
+            json_string(&diagnostic.message),
+            file,
+            span,
+            notes
+        );
+    }
+}
+
+/// A position as a JSON object, with lines and columns starting at 1 like the human output
+fn json_position(position: Position) -> String {
+    format!(
+        "{{\"line\":{},\"column\":{}}}",
+        position.line + 1,
+        position.column + 1
+    )
+}
+
+/// A string as a quoted JSON string, escaping anything that needs it
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            '\n' => out += "\\n",
+            '\r' => out += "\\r",
+            '\t' => out += "\\t",
+            c if c.is_control() => out += &format!("\\u{:04x}", c as u32),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
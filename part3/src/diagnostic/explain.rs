@@ -0,0 +1,57 @@
+//! EGRE 591 part3 - Nathan Rowan and Trevin Vaughan
+//!
+//! Longer explanations of each diagnostic code, shown with `--explain`. Codes are a `T`
+//! followed by two digits for the pass that reports them and two digits for the kind:
+//! 01 is the scanner, 02 the parser, 03 semantic analysis, 04 code generation and 05
+//! the interpreter.
+
+/// Every code along with its explanation, in order
+const EXPLANATIONS: &[(&str, &str)] = &[
+    ("T0101", include_str!("explanations/T0101.txt")),
+    ("T0102", include_str!("explanations/T0102.txt")),
+    ("T0103", include_str!("explanations/T0103.txt")),
+    ("T0104", include_str!("explanations/T0104.txt")),
+    ("T0105", include_str!("explanations/T0105.txt")),
+    ("T0106", include_str!("explanations/T0106.txt")),
+    ("T0107", include_str!("explanations/T0107.txt")),
+    ("T0108", include_str!("explanations/T0108.txt")),
+    ("T0109", include_str!("explanations/T0109.txt")),
+    ("T0110", include_str!("explanations/T0110.txt")),
+    ("T0111", include_str!("explanations/T0111.txt")),
+    ("T0112", include_str!("explanations/T0112.txt")),
+    ("T0113", include_str!("explanations/T0113.txt")),
+    ("T0201", include_str!("explanations/T0201.txt")),
+    ("T0301", include_str!("explanations/T0301.txt")),
+    ("T0302", include_str!("explanations/T0302.txt")),
+    ("T0303", include_str!("explanations/T0303.txt")),
+    ("T0304", include_str!("explanations/T0304.txt")),
+    ("T0305", include_str!("explanations/T0305.txt")),
+    ("T0306", include_str!("explanations/T0306.txt")),
+    ("T0307", include_str!("explanations/T0307.txt")),
+    ("T0308", include_str!("explanations/T0308.txt")),
+    ("T0309", include_str!("explanations/T0309.txt")),
+    ("T0310", include_str!("explanations/T0310.txt")),
+    ("T0313", include_str!("explanations/T0313.txt")),
+    ("T0314", include_str!("explanations/T0314.txt")),
+    ("T0315", include_str!("explanations/T0315.txt")),
+    ("T0316", include_str!("explanations/T0316.txt")),
+    ("T0317", include_str!("explanations/T0317.txt")),
+    ("T0318", include_str!("explanations/T0318.txt")),
+    ("T0403", include_str!("explanations/T0403.txt")),
+    ("T0404", include_str!("explanations/T0404.txt")),
+    ("T0501", include_str!("explanations/T0501.txt")),
+    ("T0502", include_str!("explanations/T0502.txt")),
+    ("T0503", include_str!("explanations/T0503.txt")),
+    ("T0504", include_str!("explanations/T0504.txt")),
+    ("T0505", include_str!("explanations/T0505.txt")),
+    ("T0506", include_str!("explanations/T0506.txt")),
+    ("T0507", include_str!("explanations/T0507.txt")),
+];
+
+/// Get the explanation of a code, ignoring case
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(c, _)| c.eq_ignore_ascii_case(code))
+        .map(|(_, explanation)| *explanation)
+}
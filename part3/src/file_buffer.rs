@@ -11,7 +11,10 @@ use std::{
     path::Path,
 };
 
-use crate::context::Context;
+use crate::{
+    context::Context,
+    span::{Position, Span},
+};
 
 /// An iterator over the characters in a file.
 /// Internally buffers by line.
@@ -53,15 +56,29 @@ impl FileBuffer {
 
     /// Get context for a warning or error
     pub fn context<T: Display>(&self, t: T) -> Option<Context<T>> {
+        // the error is at the current character
+        self.context_from(t, self.position())
+    }
+
+    /// Get context for a warning or error covering everything from `start` up to the
+    /// current character, which must be on the same line
+    pub fn context_from<T: Display>(&self, t: T, start: Position) -> Option<Context<T>> {
         Some(Context::new(
             t,
             self.line.clone()?,
-            self.line_num,
-            self.line_index,
+            Span::new(start, self.position()),
             self.file_name.clone(),
         ))
     }
 
+    /// Gets the position of the current character
+    pub fn position(&self) -> Position {
+        Position {
+            line: self.line_num,
+            column: self.line_index,
+        }
+    }
+
     /// Gets the current character
     pub fn get_char(&mut self) -> Option<char> {
         let line = self.line.as_ref()?;
@@ -0,0 +1,531 @@
+//! EGRE 591 part3 - Nathan Rowan and Trevin Vaughan
+//!
+//! Tree-walking interpreter for ToyC programs. This runs a checked AST directly, without a JVM,
+//! and is the reference for how compiled programs should behave.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Display,
+    io::{BufRead, Write},
+    thread,
+};
+
+use crate::{
+    diagnostic::Coded,
+    parser::ast::Operator,
+    semantic::typed::{
+        Expression, ExpressionKind, Function, Program, Statement, Storage, Type, Variable,
+    },
+    span::Span,
+};
+
+/// Deepest nesting of function calls before the program is stopped
+const MAX_CALL_DEPTH: usize = 10_000;
+
+/// Size of the stack of the thread running the program, which must fit [MAX_CALL_DEPTH] calls
+const STACK_SIZE: usize = 1 << 30;
+
+/// Errors that can happen while running a program
+#[derive(Debug)]
+pub enum Error {
+    /// A variable was used before it was given a value
+    UninitializedVariable(String),
+    /// Division or remainder by zero
+    DivisionByZero,
+    /// Too many nested function calls
+    StackOverflow,
+    /// Input which isn't an integer was read
+    InvalidInput(String),
+    /// Input ran out during a read statement
+    EndOfInput,
+    /// An element outside of an array was used
+    IndexOutOfBounds {
+        /// the index of the element
+        index: i32,
+        /// the length of the array
+        length: usize,
+        /// where the element was used
+        span: Span,
+    },
+    /// Reading or writing failed
+    Io(std::io::Error),
+}
+
+impl Error {
+    /// Where in the program the error happened, if it is known
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Error::IndexOutOfBounds { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::UninitializedVariable(id) => {
+                write!(f, "variable {} was used before being assigned a value", id)
+            }
+            Error::DivisionByZero => write!(f, "cannot divide by zero"),
+            Error::StackOverflow => {
+                write!(f, "function calls nested more than {} deep", MAX_CALL_DEPTH)
+            }
+            Error::InvalidInput(input) => write!(f, "input {} is not an integer", input),
+            Error::EndOfInput => write!(f, "ran out of input while reading"),
+            Error::IndexOutOfBounds { index, length, .. } => write!(
+                f,
+                "index {} is out of bounds for an array of length {}",
+                index, length
+            ),
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Coded for Error {
+    fn code(&self) -> &'static str {
+        match self {
+            Error::UninitializedVariable(_) => "T0501",
+            Error::DivisionByZero => "T0502",
+            Error::StackOverflow => "T0503",
+            Error::InvalidInput(_) => "T0504",
+            Error::EndOfInput => "T0505",
+            Error::IndexOutOfBounds { .. } => "T0507",
+            Error::Io(_) => "T0506",
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// Values which expressions evaluate to
+#[derive(Debug, Clone, Copy)]
+enum Value<'a> {
+    /// An integer (also used for chars and booleans)
+    Int(i32),
+    /// A string literal, which can only be written
+    Str(&'a str),
+}
+
+/// How control leaves a statement
+enum Flow {
+    /// Continue with the next statement
+    Normal,
+    /// Exit the innermost loop or switch
+    Break,
+    /// Skip to the next iteration of the innermost loop
+    Continue,
+    /// Return from the current function
+    Return(i32),
+}
+
+/// Values stored in variables
+#[derive(Debug, Clone)]
+enum Cell {
+    /// A number (also used for chars)
+    Int(i32),
+    /// The elements of an array
+    Array(Vec<i32>),
+}
+
+impl Cell {
+    /// The value a variable of the given type starts out with, if it has one.
+    ///
+    /// Arrays are created when they are declared, with every element set to 0.
+    fn new(var_type: Type) -> Option<Self> {
+        var_type
+            .length()
+            .map(|length| Cell::Array(vec![0; length as usize]))
+    }
+}
+
+/// Local variables of a function call, indexed by offset.
+///
+/// Variables without a value have been declared but not assigned to.
+type Locals = Vec<Option<Cell>>;
+
+/// Run a program, returning the value returned by main
+///
+/// # Errors
+///
+/// Fails if the program does something invalid while it is running, see [Error].
+///
+/// # Panics
+///
+/// Panics if the interpreter itself panics.
+pub fn run<R: BufRead + Send, W: Write + Send>(
+    ast: &Program,
+    input: R,
+    output: W,
+) -> Result<i32, Error> {
+    // function calls recurse in the interpreter, so it gets a thread with a bigger stack
+    thread::scope(|scope| {
+        thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, || {
+                let mut interpreter = Interpreter::new(ast, input, output);
+
+                let result = interpreter.call("main", Vec::new());
+                interpreter.output.flush()?;
+
+                result
+            })?
+            .join()
+            .expect("interpreter thread panicked")
+    })
+}
+
+/// State of a running program
+struct Interpreter<'a, R: BufRead, W: Write> {
+    /// every function, by name
+    functions: HashMap<&'a str, &'a Function>,
+    /// global variables, which start out as 0
+    globals: HashMap<&'a str, Cell>,
+    /// number of function calls currently running
+    depth: usize,
+    /// standard input
+    input: R,
+    /// words of input which have been read but not used
+    words: VecDeque<String>,
+    /// standard output
+    output: W,
+}
+
+impl<'a, R: BufRead, W: Write> Interpreter<'a, R, W> {
+    /// Set up the functions and global variables of a program
+    fn new(ast: &'a Program, input: R, output: W) -> Self {
+        Self {
+            functions: ast
+                .functions
+                .iter()
+                .map(|function| (function.name.as_str(), function))
+                .collect(),
+            globals: ast
+                .globals
+                .iter()
+                .map(|global| {
+                    let cell = Cell::new(global.var_type).unwrap_or(Cell::Int(0));
+                    (global.name.as_str(), cell)
+                })
+                .collect(),
+            depth: 0,
+            input,
+            words: VecDeque::new(),
+            output,
+        }
+    }
+
+    /// Call a function with the given arguments, returning its result
+    fn call(&mut self, id: &str, args: Vec<i32>) -> Result<i32, Error> {
+        let function = self.functions[id];
+
+        if self.depth == MAX_CALL_DEPTH {
+            return Err(Error::StackOverflow);
+        }
+
+        // parameters take up the first offsets
+        let mut locals = vec![None; function.locals];
+        for (local, arg) in locals.iter_mut().zip(args) {
+            *local = Some(Cell::Int(arg));
+        }
+
+        self.depth += 1;
+        let flow = self.execute(&function.body, &mut locals);
+        self.depth -= 1;
+
+        match flow? {
+            // functions that run off the end return 0, like main in C
+            Flow::Normal | Flow::Break | Flow::Continue => Ok(0),
+            Flow::Return(value) => Ok(value),
+        }
+    }
+
+    /// Execute a statement
+    fn execute(&mut self, statement: &'a Statement, locals: &mut Locals) -> Result<Flow, Error> {
+        match statement {
+            Statement::Expr(e) => {
+                self.evaluate_int(e, locals)?;
+            }
+            Statement::Break => return Ok(Flow::Break),
+            Statement::Continue => return Ok(Flow::Continue),
+            Statement::Block(vars, statements) => {
+                // variables start out unassigned each time the block is entered
+                for var in vars {
+                    if let Storage::Local(offset) = var.storage {
+                        locals[offset] = Cell::new(var.var_type);
+                    }
+                }
+
+                for statement in statements {
+                    let flow = self.execute(statement, locals)?;
+                    if !matches!(flow, Flow::Normal) {
+                        return Ok(flow);
+                    }
+                }
+            }
+            Statement::If(condition, statement, else_statement) => {
+                if self.evaluate_int(condition, locals)? != 0 {
+                    return self.execute(statement, locals);
+                } else if let Some(else_statement) = else_statement {
+                    return self.execute(else_statement, locals);
+                }
+            }
+            Statement::Null => (),
+            Statement::Return(e) => return Ok(Flow::Return(self.evaluate_int(e, locals)?)),
+            Statement::While(condition, statement) => {
+                while self.evaluate_int(condition, locals)? != 0 {
+                    match self.execute(statement, locals)? {
+                        Flow::Normal | Flow::Continue => (),
+                        Flow::Break => break,
+                        flow @ Flow::Return(_) => return Ok(flow),
+                    }
+                }
+            }
+            Statement::DoWhile(statement, condition) => loop {
+                match self.execute(statement, locals)? {
+                    Flow::Normal | Flow::Continue => (),
+                    Flow::Break => break,
+                    flow @ Flow::Return(_) => return Ok(flow),
+                }
+                // continue skips to the condition
+                if self.evaluate_int(condition, locals)? == 0 {
+                    break;
+                }
+            },
+            Statement::For(init, condition, step, statement) => {
+                if let Some(init) = init {
+                    self.evaluate_int(init, locals)?;
+                }
+
+                loop {
+                    if let Some(condition) = condition {
+                        if self.evaluate_int(condition, locals)? == 0 {
+                            break;
+                        }
+                    }
+
+                    match self.execute(statement, locals)? {
+                        Flow::Normal | Flow::Continue => (),
+                        Flow::Break => break,
+                        flow @ Flow::Return(_) => return Ok(flow),
+                    }
+
+                    // continue skips to the step
+                    if let Some(step) = step {
+                        self.evaluate_int(step, locals)?;
+                    }
+                }
+            }
+            Statement::Switch(expression, cases) => {
+                let value = self.evaluate_int(expression, locals)?;
+
+                // start at the matching case, or else the default case
+                let start = cases
+                    .iter()
+                    .position(|case| case.value == Some(value))
+                    .or_else(|| cases.iter().position(|case| case.value.is_none()));
+
+                // later cases are fallen through to, until a break
+                if let Some(start) = start {
+                    for statement in cases[start..].iter().flat_map(|case| &case.statements) {
+                        match self.execute(statement, locals)? {
+                            Flow::Normal => (),
+                            Flow::Break => break,
+                            flow @ (Flow::Continue | Flow::Return(_)) => return Ok(flow),
+                        }
+                    }
+                }
+            }
+            Statement::Read(vars) => {
+                // show any prompt before waiting for input
+                self.output.flush()?;
+
+                for var in vars {
+                    let value = match var.var_type {
+                        Type::Char => self.read_char()?,
+                        _ => self.read_int()?,
+                    };
+                    self.assign(var, value, locals);
+                }
+            }
+            Statement::Write(expressions) => {
+                for e in expressions {
+                    match self.evaluate(e, locals)? {
+                        Value::Int(n) if e.expr_type == Type::Char => {
+                            let c = u32::try_from(n).ok().and_then(char::from_u32);
+                            write!(self.output, "{}", c.unwrap_or(char::REPLACEMENT_CHARACTER))?
+                        }
+                        Value::Int(n) => write!(self.output, "{}", n)?,
+                        Value::Str(s) => write!(self.output, "{}", s)?,
+                    }
+                }
+            }
+            Statement::Newline => writeln!(self.output)?,
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    /// Read the next whitespace separated word from the input
+    fn read_word(&mut self) -> Result<String, Error> {
+        while self.words.is_empty() {
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Err(Error::EndOfInput);
+            }
+
+            self.words
+                .extend(line.split_whitespace().map(str::to_owned));
+        }
+
+        Ok(self.words.pop_front().unwrap())
+    }
+
+    /// Read the next whitespace separated integer from the input
+    fn read_int(&mut self) -> Result<i32, Error> {
+        let word = self.read_word()?;
+        word.parse().map_err(|_| Error::InvalidInput(word))
+    }
+
+    /// Read the first character of the next whitespace separated word from the input, as a
+    /// JVM char (like `Scanner.next().charAt(0)`)
+    fn read_char(&mut self) -> Result<i32, Error> {
+        let word = self.read_word()?;
+        // split_whitespace never gives an empty word
+        Ok(i32::from(word.encode_utf16().next().unwrap()))
+    }
+
+    /// Find the value of a variable
+    fn lookup(&self, var: &Variable, locals: &Locals) -> Result<i32, Error> {
+        let cell = match var.storage {
+            Storage::Local(offset) => locals[offset].as_ref(),
+            Storage::Static => Some(&self.globals[var.name.as_str()]),
+        };
+
+        match cell {
+            Some(Cell::Int(value)) => Ok(*value),
+            Some(Cell::Array(_)) => {
+                unreachable!("semantic analysis only allows arrays to be indexed")
+            }
+            None => Err(Error::UninitializedVariable(var.name.to_owned())),
+        }
+    }
+
+    /// Give a variable a new value
+    fn assign(&mut self, var: &Variable, value: i32, locals: &mut Locals) {
+        match var.storage {
+            Storage::Local(offset) => locals[offset] = Some(Cell::Int(value)),
+            Storage::Static => *self.globals.get_mut(var.name.as_str()).unwrap() = Cell::Int(value),
+        }
+    }
+
+    /// Find an element of an array, which must be inside of the array
+    fn element<'l>(
+        &'l mut self,
+        var: &Variable,
+        index: i32,
+        span: Span,
+        locals: &'l mut Locals,
+    ) -> Result<&'l mut i32, Error> {
+        let cell = match var.storage {
+            Storage::Local(offset) => locals[offset].as_mut(),
+            Storage::Static => self.globals.get_mut(var.name.as_str()),
+        };
+        let Some(Cell::Array(elements)) = cell else {
+            unreachable!("arrays are created when they are declared");
+        };
+
+        let length = elements.len();
+        usize::try_from(index)
+            .ok()
+            .and_then(|i| elements.get_mut(i))
+            .ok_or(Error::IndexOutOfBounds {
+                index,
+                length,
+                span,
+            })
+    }
+
+    /// Evaluate an expression which must be a number
+    fn evaluate_int(
+        &mut self,
+        expression: &'a Expression,
+        locals: &mut Locals,
+    ) -> Result<i32, Error> {
+        match self.evaluate(expression, locals)? {
+            Value::Int(n) => Ok(n),
+            Value::Str(_) => unreachable!("semantic analysis only allows strings to be written"),
+        }
+    }
+
+    /// Evaluate an expression. Integers wrap around on overflow, the same as on the JVM.
+    fn evaluate(
+        &mut self,
+        expression: &'a Expression,
+        locals: &mut Locals,
+    ) -> Result<Value<'a>, Error> {
+        let value = match &expression.kind {
+            ExpressionKind::Number(n) => *n,
+            ExpressionKind::Variable(var) => self.lookup(var, locals)?,
+            ExpressionKind::CharLiteral(c) => *c as i32,
+            ExpressionKind::StringLiteral(s) => return Ok(Value::Str(s)),
+            ExpressionKind::FuncCall(id, args) => {
+                // arguments are evaluated from left to right
+                let args = args
+                    .iter()
+                    .map(|arg| self.evaluate_int(arg, locals))
+                    .collect::<Result<_, _>>()?;
+
+                self.call(id, args)?
+            }
+            ExpressionKind::Assign(var, rhs) => {
+                let value = self.evaluate_int(rhs, locals)?;
+                self.assign(var, value, locals);
+                value
+            }
+            ExpressionKind::Index(var, index) => {
+                let index = self.evaluate_int(index, locals)?;
+                *self.element(var, index, expression.span, locals)?
+            }
+            // like on the JVM, the value is found before the index is checked
+            ExpressionKind::AssignIndex(var, index, rhs) => {
+                let index = self.evaluate_int(index, locals)?;
+                let value = self.evaluate_int(rhs, locals)?;
+                *self.element(var, index, expression.span, locals)? = value;
+                value
+            }
+            // the rhs of && and || is only evaluated when needed
+            ExpressionKind::Expr(Operator::BoolAnd, lhs, rhs) => i32::from(
+                self.evaluate_int(lhs, locals)? != 0 && self.evaluate_int(rhs, locals)? != 0,
+            ),
+            ExpressionKind::Expr(Operator::BoolOr, lhs, rhs) => i32::from(
+                self.evaluate_int(lhs, locals)? != 0 || self.evaluate_int(rhs, locals)? != 0,
+            ),
+            ExpressionKind::Expr(op, lhs, rhs) => {
+                let lhs = self.evaluate_int(lhs, locals)?;
+                let rhs = self.evaluate_int(rhs, locals)?;
+
+                op.apply(lhs, rhs).ok_or(Error::DivisionByZero)?
+            }
+            ExpressionKind::Minus(e) => self.evaluate_int(e, locals)?.wrapping_neg(),
+            ExpressionKind::Not(e) => i32::from(self.evaluate_int(e, locals)? == 0),
+            ExpressionKind::BitNot(e) => !self.evaluate_int(e, locals)?,
+            ExpressionKind::ToChar(e) => i32::from(self.evaluate_int(e, locals)? as u16),
+            // only the chosen side is evaluated
+            ExpressionKind::Conditional(condition, true_exp, false_exp) => {
+                return if self.evaluate_int(condition, locals)? != 0 {
+                    self.evaluate(true_exp, locals)
+                } else {
+                    self.evaluate(false_exp, locals)
+                };
+            }
+        };
+
+        Ok(Value::Int(value))
+    }
+}
@@ -9,21 +9,32 @@
 #![warn(clippy::missing_panics_doc)]
 #![warn(clippy::missing_errors_doc)]
 
-use std::{fs::write, path::PathBuf, process::ExitCode};
+use std::{
+    fs::write,
+    io::{stderr, stdin, stdout, BufReader},
+    path::PathBuf,
+    process::ExitCode,
+};
 
 use clap::{Parser as ClapParser, ValueEnum};
-use code_gen::jsm::generate_code;
-use colored::Colorize;
+use code_gen::{jsm::generate_code, Error as CodeGenError};
 
 pub mod code_gen;
 pub mod context;
+pub mod diagnostic;
 pub mod file_buffer;
+pub mod interpreter;
 pub mod parser;
 pub mod scanner;
+pub mod semantic;
+pub mod source;
+pub mod span;
 
 use context::MaybeContext;
+use diagnostic::{explain::explain, Diagnostic, Human, Json, Severity, Sink};
 use parser::{ast::Program, error::Error as ParserError, Parser};
-use scanner::Scanner;
+use scanner::{error::Error as ScannerError, Scanner};
+use source::Source;
 
 /// Command line arguments accepted by the scanner
 #[derive(Clone, PartialEq, Eq, ClapParser)]
@@ -45,12 +56,24 @@ struct Args {
     /// dump the symbol table(s)
     #[arg(short, long)]
     symbol: bool,
-    /// dump the generated program
-    #[arg(short, long)]
+    /// dump the generated program (the short flag is -C, since -c is --class)
+    #[arg(short = 'C', long)]
     code: bool,
+    /// format of the target file
+    #[arg(short, long, value_enum, default_value_t = Emit::Jasmin)]
+    emit: Emit,
+    /// run the program with the interpreter instead of compiling it
+    #[arg(short, long)]
+    run: bool,
     /// display all information
     #[arg(short, long)]
     verbose: bool,
+    /// format of error and warning messages
+    #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
+    /// explain an error or warning code in detail, such as T0101
+    #[arg(long, value_name = "CODE")]
+    explain: Option<String>,
     /// toyc source files
     input_files: Vec<PathBuf>,
 }
@@ -66,13 +89,50 @@ enum DebugLevel {
     Parser,
 }
 
+/// Formats of the target file
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Emit {
+    /// Jasmin assembly, only written when a target file name is given
+    Jasmin,
+    /// A class file, named after the class unless a target file name is given
+    Class,
+}
+
+/// Formats of error and warning messages
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum MessageFormat {
+    /// Messages pointing at the source, meant to be read by people
+    Human,
+    /// One JSON object per line, meant to be read by other programs
+    Json,
+}
+
 fn main() -> ExitCode {
     // parse command line arguments
     let args = Args::parse();
 
+    // every error and warning goes through here
+    let mut sink: Box<dyn Sink> = match args.message_format {
+        MessageFormat::Human => Box::new(Human::new(stderr())),
+        MessageFormat::Json => Box::new(Json::new(stderr())),
+    };
+
+    if let Some(code) = &args.explain {
+        let Some(explanation) = explain(code) else {
+            sink.emit(&Diagnostic::new(
+                Severity::Error,
+                format!("{} is not an error or warning code", code),
+            ));
+            return ExitCode::FAILURE;
+        };
+
+        print!("{}", explanation);
+        return ExitCode::SUCCESS;
+    }
+
     // if the list of input files is empty throw an error
     if args.input_files.is_empty() {
-        eprintln!("{} Missing input files!", "[ERROR]".red());
+        sink.emit(&Diagnostic::new(Severity::Error, "Missing input files!"));
         return ExitCode::FAILURE;
     }
 
@@ -85,58 +145,136 @@ fn main() -> ExitCode {
     let debug_scanner = matches!(args.debug, Some(DebugLevel::All | DebugLevel::Scanner));
     let debug_parser = matches!(args.debug, Some(DebugLevel::All | DebugLevel::Parser));
 
+    // return value of the last program that was run
+    let mut exit_code = ExitCode::SUCCESS;
+
     for path in args.input_files {
         // front-end of the compiler
-        let parse = || {
-            let scanner = Scanner::new(&path, debug_scanner, verbose).map_err(ParserError::from)?;
+        let mut parse = || {
+            let scanner = Scanner::new(&path, debug_scanner, verbose)
+                .map_err(|e| vec![ParserError::from(e).into()])?;
 
-            let parser = Parser::new(scanner, debug_parser, verbose)?;
+            let parser = Parser::new(scanner, debug_parser, verbose).map_err(|e| vec![e.into()])?;
 
-            let ast = parser.parse()?;
+            // every error is reported, but the program can't be compiled with any
+            let parsed = parser.parse();
+            for w in parsed.warnings {
+                sink.emit(&w.into_diagnostic(Severity::Warning));
+            }
+            if !parsed.errors.is_empty() {
+                return Err(parsed.errors.into_iter().map(MaybeContext::from).collect());
+            }
+            let ast = parsed.program;
 
             if args.abstract_ {
                 println!("<< Abstract Syntax >>\n{}", ast)
             }
 
-            Ok::<Program, MaybeContext<ParserError>>(ast)
+            Ok::<Program, Vec<MaybeContext<ParserError>>>(ast)
         };
 
         let ast = match parse() {
             Ok(ast) => ast,
-            Err(e) => {
-                eprintln!("{} {}", "[ERROR]".red(), e);
+            Err(errors) => {
+                for e in errors {
+                    sink.emit(&e.into_diagnostic(Severity::Error));
+                }
                 continue;
             }
         };
 
-        // back-end of the compiler
-        let file_name = path.file_name().unwrap().to_str().unwrap();
+        // later errors are found after the scanner is done with the file
+        let source = match Source::new(&path) {
+            Ok(source) => source,
+            Err(e) => {
+                sink.emit(&Diagnostic::coded(Severity::Error, ScannerError::from(e)));
+                continue;
+            }
+        };
 
+        // middle of the compiler
         if args.symbol {
             println!("<< Symbol Table(s) >>");
         }
 
-        let code = match generate_code(
-            &ast,
-            file_name,
-            args.class.as_ref().unwrap_or(&String::from("ToyC")),
-            args.symbol,
-        ) {
-            Ok(code) => code,
+        let ast = match semantic::analyze(&ast, &source, args.symbol) {
+            Ok(ast) => ast,
             Err(e) => {
-                eprintln!("{} {}", "[ERROR]".red(), e);
+                sink.emit(&e.into_diagnostic(Severity::Error));
                 continue;
             }
         };
 
+        if args.run {
+            exit_code = match interpreter::run(&ast, BufReader::new(stdin()), stdout()) {
+                // like a native process, only the lowest byte is kept
+                Ok(value) => ExitCode::from(value as u8),
+                Err(e) => {
+                    // errors are shown with the code they happened at, when it is known
+                    let diagnostic = match e.span() {
+                        Some(span) => source.context(e, span).into_diagnostic(Severity::Error),
+                        None => Diagnostic::coded(Severity::Error, e),
+                    };
+                    sink.emit(&diagnostic);
+                    ExitCode::FAILURE
+                }
+            };
+            continue;
+        }
+
+        // back-end of the compiler
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+
+        let class = generate_code(
+            &ast,
+            file_name,
+            args.class.as_ref().unwrap_or(&String::from("ToyC")),
+        );
+
         if args.code {
-            println!("<< Generated Code >>\n{}", code)
+            println!("<< Generated Code >>\n{}", class)
         }
 
-        if let Some(output) = &args.output {
-            write(output, &code).unwrap();
+        match args.emit {
+            Emit::Jasmin => {
+                if let Some(output) = &args.output {
+                    if let Err(e) = write(output, class.to_string()) {
+                        sink.emit(&Diagnostic::coded(
+                            Severity::Error,
+                            CodeGenError::Output(output.to_owned(), e),
+                        ));
+                        exit_code = ExitCode::FAILURE;
+                        continue;
+                    }
+                }
+            }
+            Emit::Class => {
+                let bytes = match class.to_bytes() {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        sink.emit(&Diagnostic::coded(Severity::Error, e));
+                        exit_code = ExitCode::FAILURE;
+                        continue;
+                    }
+                };
+
+                // the jvm looks for classes in files of the same name
+                let output = match &args.output {
+                    Some(output) => output.to_owned(),
+                    None => format!("{}.class", class.name),
+                };
+
+                if let Err(e) = write(&output, bytes) {
+                    sink.emit(&Diagnostic::coded(
+                        Severity::Error,
+                        CodeGenError::Output(output, e),
+                    ));
+                    exit_code = ExitCode::FAILURE;
+                    continue;
+                }
+            }
         }
     }
 
-    ExitCode::SUCCESS
+    exit_code
 }
@@ -4,15 +4,19 @@
 
 use std::fmt::Display;
 
-use crate::scanner::token::{AddOp, Keyword, MulOp, RelOp, Token};
+use crate::{
+    scanner::token::{AddOp, BitOp, Keyword, MulOp, RelOp, Token},
+    span::{Span, Spanned},
+};
 
 mod printing;
 
 /// Identifiers, which are represented as strings
 pub type Identifier = String;
 
-/// Variable definitions, which include a list of identifiers and a type
-pub type VarDef = (Vec<Identifier>, Type);
+/// Variable definitions, which include a list of identifiers, each with where it is, a type
+/// and where the whole definition is
+pub type VarDef = (Vec<Spanned<Identifier>>, Type, Span);
 
 impl TryFrom<Token> for Identifier {
     type Error = ();
@@ -29,24 +33,44 @@ impl TryFrom<Token> for Identifier {
 #[derive(Debug, Clone)]
 pub struct Program(pub Vec<Definition>);
 
+/// A definition along with where it is in the source file
+#[derive(Debug, Clone)]
+pub struct Definition {
+    /// what is being defined
+    pub kind: DefinitionKind,
+    /// from the type to the end of the definition
+    pub span: Span,
+}
+
 /// Definitions allowed in the AST
 #[derive(Debug, Clone)]
-pub enum Definition {
+pub enum DefinitionKind {
     /// a function definition
     Func(Identifier, Type, Vec<VarDef>, Statement),
     /// a variable definition
-    Var(Vec<Identifier>, Type),
+    Var(Vec<Spanned<Identifier>>, Type),
+}
+
+/// A statement along with where it is in the source file
+#[derive(Debug, Clone)]
+pub struct Statement {
+    /// what the statement does
+    pub kind: StatementKind,
+    /// from the first token to the last token of the statement
+    pub span: Span,
 }
 
 /// All statements allowed in the AST
 ///
 /// Note: sub-statements must be heap-allocated to prevent infinitely sized types
 #[derive(Debug, Clone)]
-pub enum Statement {
+pub enum StatementKind {
     /// An expression statement
     Expr(Expression),
     /// A break statement
     Break,
+    /// A continue statement
+    Continue,
     /// A block with variable definitions and more statements
     Block(Vec<VarDef>, Vec<Statement>),
     /// An if statement
@@ -57,45 +81,96 @@ pub enum Statement {
     Return(Option<Expression>),
     /// A while statement
     While(Expression, Box<Statement>),
-    /// A read statement
-    Read(Vec<Identifier>),
+    /// A do-while statement, with its body before its condition
+    DoWhile(Box<Statement>, Expression),
+    /// A for statement, with an optional initializer, condition and step before its body
+    For(
+        Option<Box<Expression>>,
+        Option<Box<Expression>>,
+        Option<Box<Expression>>,
+        Box<Statement>,
+    ),
+    /// A switch statement, with its cases in the order they appear
+    Switch(Expression, Vec<SwitchCase>),
+    /// A read statement, with the span of each variable
+    Read(Vec<Spanned<Identifier>>),
     /// A write statement
     Write(Vec<Expression>),
     /// A newline statement
     Newline,
 }
 
+/// A case of a switch statement, whose statements fall through to the next case
+#[derive(Debug, Clone)]
+pub struct SwitchCase {
+    /// the constant compared against, or [None] for the default case
+    pub value: Option<Expression>,
+    /// statements run when the case matches
+    pub statements: Vec<Statement>,
+    /// from the `case` or `default` keyword to the `:`
+    pub span: Span,
+}
+
+/// An expression along with where it is in the source file
+#[derive(Debug, Clone)]
+pub struct Expression {
+    /// what the expression does
+    pub kind: ExpressionKind,
+    /// from the first token to the last token of the expression, including parentheses
+    pub span: Span,
+}
+
+impl Expression {
+    /// Construct a new expression
+    pub fn new(kind: ExpressionKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// Construct a binary operation, spanning both of its operands
+    pub fn binary(op: Operator, lhs: Expression, rhs: Expression) -> Self {
+        let span = lhs.span.to(rhs.span);
+        Self::new(ExpressionKind::Expr(op, Box::new(lhs), Box::new(rhs)), span)
+    }
+}
+
 /// All expressions allowed in the AST
 ///
 /// Note: sub-expressions must be heap-allocated to prevent infinitely sized types
 #[derive(Debug, Clone)]
-pub enum Expression {
+pub enum ExpressionKind {
     /// A number
-    Number(String),
+    Number(i32),
     /// An identifier
     Identifier(Identifier),
     /// A character literal
-    CharLiteral(Option<char>),
+    CharLiteral(char),
     /// A string literal
     StringLiteral(String),
     /// A function call, including an identifier and a list of input expressions
     FuncCall(Identifier, Vec<Expression>),
+    /// An element of an array, including an identifier and the index of the element
+    Index(Identifier, Box<Expression>),
     /// A binary operation with a left and right hand sub-expression
     Expr(Operator, Box<Expression>, Box<Expression>),
     /// Unary negation on numbers
     Minus(Box<Expression>),
     /// Unary negation on booleans
     Not(Box<Expression>),
+    /// Bitwise complement on numbers
+    BitNot(Box<Expression>),
+    /// A conditional expression, including the condition and the values when it is true
+    /// and when it is false
+    Conditional(Box<Expression>, Box<Expression>, Box<Expression>),
 }
 
-impl TryFrom<Token> for Expression {
+impl TryFrom<Token> for ExpressionKind {
     type Error = ();
 
     fn try_from(value: Token) -> Result<Self, Self::Error> {
         match value {
-            Token::Number(x) => Ok(Expression::Number(x)),
-            Token::CharLiteral(x) => Ok(Expression::CharLiteral(x)),
-            Token::StringLiteral(x) => Ok(Expression::StringLiteral(x)),
+            Token::Number(x) => Ok(ExpressionKind::Number(x)),
+            Token::CharLiteral(x) => Ok(ExpressionKind::CharLiteral(x)),
+            Token::StringLiteral(x) => Ok(ExpressionKind::StringLiteral(x)),
             _ => Err(()),
         }
     }
@@ -130,10 +205,52 @@ pub enum Operator {
     GtEq,
     /// !=
     Neq,
+    /// &
+    BitAnd,
+    /// |
+    BitOr,
+    /// ^
+    BitXor,
+    /// <<
+    Shl,
+    /// >>
+    Shr,
     /// =
     Assign,
 }
 
+impl Operator {
+    /// Apply the operator to two values. Integers wrap around on overflow, the same as on the
+    /// JVM, and [None] is given when dividing by zero.
+    pub fn apply(self, lhs: i32, rhs: i32) -> Option<i32> {
+        let value = match self {
+            Operator::Add => lhs.wrapping_add(rhs),
+            Operator::Sub => lhs.wrapping_sub(rhs),
+            Operator::Mul => lhs.wrapping_mul(rhs),
+            Operator::Div | Operator::Mod if rhs == 0 => return None,
+            Operator::Div => lhs.wrapping_div(rhs),
+            Operator::Mod => lhs.wrapping_rem(rhs),
+            Operator::BoolOr => i32::from(lhs != 0 || rhs != 0),
+            Operator::BoolAnd => i32::from(lhs != 0 && rhs != 0),
+            Operator::LtEq => i32::from(lhs <= rhs),
+            Operator::Lt => i32::from(lhs < rhs),
+            Operator::Eq => i32::from(lhs == rhs),
+            Operator::Gt => i32::from(lhs > rhs),
+            Operator::GtEq => i32::from(lhs >= rhs),
+            Operator::Neq => i32::from(lhs != rhs),
+            Operator::BitAnd => lhs & rhs,
+            Operator::BitOr => lhs | rhs,
+            Operator::BitXor => lhs ^ rhs,
+            // only the lowest 5 bits of the shift distance are used, like on the JVM
+            Operator::Shl => lhs.wrapping_shl(rhs as u32),
+            Operator::Shr => lhs.wrapping_shr(rhs as u32),
+            Operator::Assign => unreachable!("an assignment doesn't combine two values"),
+        };
+
+        Some(value)
+    }
+}
+
 impl From<AddOp> for Operator {
     fn from(value: AddOp) -> Self {
         match value {
@@ -168,6 +285,18 @@ impl From<RelOp> for Operator {
     }
 }
 
+impl From<BitOp> for Operator {
+    fn from(value: BitOp) -> Self {
+        match value {
+            BitOp::And => Operator::BitAnd,
+            BitOp::Or => Operator::BitOr,
+            BitOp::Xor => Operator::BitXor,
+            BitOp::Shl => Operator::Shl,
+            BitOp::Shr => Operator::Shr,
+        }
+    }
+}
+
 impl TryFrom<Token> for Operator {
     type Error = ();
 
@@ -176,6 +305,7 @@ impl TryFrom<Token> for Operator {
             Token::AddOp(x) => Ok(x.into()),
             Token::MulOp(x) => Ok(x.into()),
             Token::RelOp(x) => Ok(x.into()),
+            Token::BitOp(x) => Ok(x.into()),
             Token::AssignOp => Ok(Operator::Assign),
             _ => Err(()),
         }
@@ -189,6 +319,25 @@ pub enum Type {
     Int,
     /// Characters
     Char,
+    /// Arrays of integers, with their length as written, which semantic analysis checks
+    IntArray(i32),
+    /// Arrays of characters, with their length as written, which semantic analysis checks
+    CharArray(i32),
+}
+
+impl Type {
+    /// An array with elements of this type
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is already an array type, since arrays only have one dimension.
+    pub fn array(self, length: i32) -> Self {
+        match self {
+            Type::Int => Type::IntArray(length),
+            Type::Char => Type::CharArray(length),
+            Type::IntArray(_) | Type::CharArray(_) => panic!("arrays only have one dimension"),
+        }
+    }
 }
 
 impl TryFrom<Token> for Type {
@@ -208,6 +357,8 @@ impl Display for Type {
         match self {
             Type::Int => write!(f, "int"),
             Type::Char => write!(f, "char"),
+            Type::IntArray(length) => write!(f, "int[{}]", length),
+            Type::CharArray(length) => write!(f, "char[{}]", length),
         }
     }
 }
@@ -5,6 +5,7 @@
 use std::fmt::Display;
 
 use super::*;
+use crate::scanner::token::escape;
 
 /// Indentation size
 const INDENT_SIZE: usize = 2;
@@ -69,6 +70,8 @@ enum PrintableReference<'a> {
     Identifier(&'a Identifier),
     /// Statement
     Statement(&'a Statement),
+    /// Case of a switch statement
+    SwitchCase(&'a SwitchCase),
     /// Expression
     Expression(&'a Expression),
     /// Operator
@@ -89,6 +92,7 @@ impl<'a> PrettyPrint for PrintableReference<'a> {
             PrintableReference::VarDef(v) => v.print(indent),
             PrintableReference::Identifier(i) => i.print(indent),
             PrintableReference::Statement(s) => s.print(indent),
+            PrintableReference::SwitchCase(c) => c.print(indent),
             PrintableReference::Expression(e) => e.print(indent),
             PrintableReference::Operator(o) => o.print(indent),
             PrintableReference::Type(t) => t.print(indent),
@@ -104,11 +108,12 @@ impl<'a> PrettyPrint for PrintableReference<'a> {
             PrintableReference::VarDef(v) => v.is_short(),
             PrintableReference::Identifier(i) => i.is_short(),
             PrintableReference::Statement(s) => s.is_short(),
+            PrintableReference::SwitchCase(c) => c.is_short(),
             PrintableReference::Expression(e) => e.is_short(),
             PrintableReference::Operator(o) => o.is_short(),
             PrintableReference::Type(t) => t.is_short(),
             PrintableReference::List(l) => l.iter().all(|e| e.is_short()),
-            PrintableReference::Option(o) => o.map_or(true, |o| o.is_short()),
+            PrintableReference::Option(o) => o.is_none_or(|o| o.is_short()),
         }
     }
 }
@@ -143,6 +148,12 @@ impl<'a> From<&'a Statement> for PrintableReference<'a> {
     }
 }
 
+impl<'a> From<&'a SwitchCase> for PrintableReference<'a> {
+    fn from(value: &'a SwitchCase) -> Self {
+        Self::SwitchCase(value)
+    }
+}
+
 impl<'a> From<&'a Expression> for PrintableReference<'a> {
     fn from(value: &'a Expression) -> Self {
         Self::Expression(value)
@@ -243,8 +254,8 @@ impl Display for Program {
 
 impl PrettyPrint for Definition {
     fn print(&self, indent: usize) -> String {
-        match self {
-            Definition::Func(id, ast_type, args, body) => {
+        match &self.kind {
+            DefinitionKind::Func(id, ast_type, args, body) => {
                 let args: Vec<_> = args.iter().map(Into::into).collect();
                 format!(
                     "funcDef{}",
@@ -254,8 +265,11 @@ impl PrettyPrint for Definition {
                     )
                 )
             }
-            Definition::Var(id, ast_type) => {
-                let id: Vec<_> = id.iter().map(PrintableReference::Identifier).collect();
+            DefinitionKind::Var(id, ast_type) => {
+                let id: Vec<_> = id
+                    .iter()
+                    .map(|id| PrintableReference::Identifier(&id.node))
+                    .collect();
                 format!(
                     "varDef{}",
                     print_args([(&id[..]).into(), ast_type.into()], indent)
@@ -271,7 +285,11 @@ impl PrettyPrint for Definition {
 
 impl PrettyPrint for VarDef {
     fn print(&self, indent: usize) -> String {
-        let id: Vec<_> = self.0.iter().map(PrintableReference::Identifier).collect();
+        let id: Vec<_> = self
+            .0
+            .iter()
+            .map(|id| PrintableReference::Identifier(&id.node))
+            .collect();
 
         format!(
             "varDef{}",
@@ -296,10 +314,11 @@ impl PrettyPrint for Identifier {
 
 impl PrettyPrint for Statement {
     fn print(&self, indent: usize) -> String {
-        match self {
-            Statement::Expr(e) => format!("exprState{}", print_args([e.into()], indent)),
-            Statement::Break => "breakState()".to_string(),
-            Statement::Block(var_def, statements) => {
+        match &self.kind {
+            StatementKind::Expr(e) => format!("exprState{}", print_args([e.into()], indent)),
+            StatementKind::Break => "breakState()".to_string(),
+            StatementKind::Continue => "continueState()".to_string(),
+            StatementKind::Block(var_def, statements) => {
                 let var_def: Vec<_> = var_def.iter().map(PrintableReference::VarDef).collect();
                 let statements: Vec<_> = statements
                     .iter()
@@ -313,7 +332,7 @@ impl PrettyPrint for Statement {
                     )
                 )
             }
-            Statement::If(condition, if_block, else_block) => {
+            StatementKind::If(condition, if_block, else_block) => {
                 format!(
                     "ifState{}",
                     print_args(
@@ -326,27 +345,78 @@ impl PrettyPrint for Statement {
                     )
                 )
             }
-            Statement::Null => "nullState()".to_string(),
-            Statement::Return(expr) => format!(
+            StatementKind::Null => "nullState()".to_string(),
+            StatementKind::Return(expr) => format!(
                 "returnState{}",
                 print_args(
                     [(&expr.as_ref().map(PrintableReference::Expression)).into()],
                     indent
                 )
             ),
-            Statement::While(condition, body) => format!(
+            StatementKind::While(condition, body) => format!(
                 "whileState{}",
                 print_args([condition.into(), (&**body).into()], indent)
             ),
-            Statement::Read(args) => {
-                let args: Vec<_> = args.iter().map(PrintableReference::Identifier).collect();
+            StatementKind::DoWhile(body, condition) => format!(
+                "doWhileState{}",
+                print_args([(&**body).into(), condition.into()], indent)
+            ),
+            StatementKind::For(init, condition, step, body) => format!(
+                "forState{}",
+                print_args(
+                    [
+                        (&init.as_deref().map(PrintableReference::Expression)).into(),
+                        (&condition.as_deref().map(PrintableReference::Expression)).into(),
+                        (&step.as_deref().map(PrintableReference::Expression)).into(),
+                        (&**body).into()
+                    ],
+                    indent
+                )
+            ),
+            StatementKind::Switch(expression, cases) => {
+                let cases: Vec<_> = cases.iter().map(PrintableReference::SwitchCase).collect();
+                format!(
+                    "switchState{}",
+                    print_args([expression.into(), (&cases[..]).into()], indent)
+                )
+            }
+            StatementKind::Read(args) => {
+                let args: Vec<_> = args
+                    .iter()
+                    .map(|id| PrintableReference::Identifier(&id.node))
+                    .collect();
                 format!("readState{}", print_args([(&args[..]).into()], indent))
             }
-            Statement::Write(args) => {
+            StatementKind::Write(args) => {
                 let args: Vec<_> = args.iter().map(PrintableReference::Expression).collect();
                 format!("writeState{}", print_args([(&args[..]).into()], indent))
             }
-            Statement::Newline => "newLineState()".to_string(),
+            StatementKind::Newline => "newLineState()".to_string(),
+        }
+    }
+
+    fn is_short(&self) -> bool {
+        false
+    }
+}
+
+impl PrettyPrint for SwitchCase {
+    fn print(&self, indent: usize) -> String {
+        let statements: Vec<_> = self
+            .statements
+            .iter()
+            .map(PrintableReference::Statement)
+            .collect();
+
+        match &self.value {
+            Some(value) => format!(
+                "caseState{}",
+                print_args([value.into(), (&statements[..]).into()], indent)
+            ),
+            None => format!(
+                "defaultState{}",
+                print_args([(&statements[..]).into()], indent)
+            ),
         }
     }
 
@@ -357,37 +427,60 @@ impl PrettyPrint for Statement {
 
 impl PrettyPrint for Expression {
     fn print(&self, indent: usize) -> String {
-        match self {
-            Expression::Number(n) => n.clone(),
-            Expression::Identifier(id) => id.clone(),
-            Expression::CharLiteral(c) => c.map_or_else(String::new, |c| c.to_string()),
-            Expression::StringLiteral(s) => format!("string(\"{}\")", s),
-            Expression::FuncCall(id, args) => {
+        match &self.kind {
+            ExpressionKind::Number(n) => n.to_string(),
+            ExpressionKind::Identifier(id) => id.clone(),
+            ExpressionKind::CharLiteral(c) => escape(&c.to_string(), '\''),
+            ExpressionKind::StringLiteral(s) => format!("string(\"{}\")", escape(s, '"')),
+            ExpressionKind::FuncCall(id, args) => {
                 let args: Vec<_> = args.iter().map(PrintableReference::Expression).collect();
                 format!(
                     "funcCall{}",
                     print_args([id.into(), (&args[..]).into()], indent)
                 )
             }
-            Expression::Expr(op, lhs, rhs) => format!(
+            ExpressionKind::Index(id, index) => format!(
+                "arrayIndex{}",
+                print_args([id.into(), (&**index).into()], indent)
+            ),
+            ExpressionKind::Expr(op, lhs, rhs) => format!(
                 "expr{}",
                 print_args([op.into(), (&**lhs).into(), (&**rhs).into()], indent)
             ),
-            Expression::Minus(expr) => format!("minus{}", print_args([(&**expr).into()], indent)),
-            Expression::Not(expr) => format!("not{}", print_args([(&**expr).into()], indent)),
+            ExpressionKind::Minus(expr) => {
+                format!("minus{}", print_args([(&**expr).into()], indent))
+            }
+            ExpressionKind::Not(expr) => format!("not{}", print_args([(&**expr).into()], indent)),
+            ExpressionKind::BitNot(expr) => {
+                format!("bitNot{}", print_args([(&**expr).into()], indent))
+            }
+            ExpressionKind::Conditional(condition, true_expr, false_expr) => format!(
+                "conditional{}",
+                print_args(
+                    [
+                        (&**condition).into(),
+                        (&**true_expr).into(),
+                        (&**false_expr).into()
+                    ],
+                    indent
+                )
+            ),
         }
     }
 
     fn is_short(&self) -> bool {
-        match self {
-            Expression::Number(_) => true,
-            Expression::Identifier(_) => true,
-            Expression::CharLiteral(_) => true,
-            Expression::StringLiteral(_) => false,
-            Expression::FuncCall(_, _) => false,
-            Expression::Expr(_, _, _) => false,
-            Expression::Minus(_) => false,
-            Expression::Not(_) => false,
+        match &self.kind {
+            ExpressionKind::Number(_) => true,
+            ExpressionKind::Identifier(_) => true,
+            ExpressionKind::CharLiteral(_) => true,
+            ExpressionKind::StringLiteral(_) => false,
+            ExpressionKind::FuncCall(_, _) => false,
+            ExpressionKind::Index(_, _) => false,
+            ExpressionKind::Expr(_, _, _) => false,
+            ExpressionKind::Minus(_) => false,
+            ExpressionKind::Not(_) => false,
+            ExpressionKind::BitNot(_) => false,
+            ExpressionKind::Conditional(_, _, _) => false,
         }
     }
 }
@@ -408,6 +501,11 @@ impl PrettyPrint for Operator {
             Operator::Gt => String::from("GT"),
             Operator::GtEq => String::from("GT_EQ"),
             Operator::Neq => String::from("NEQ"),
+            Operator::BitAnd => String::from("BIT_AND"),
+            Operator::BitOr => String::from("BIT_OR"),
+            Operator::BitXor => String::from("BIT_XOR"),
+            Operator::Shl => String::from("SHL"),
+            Operator::Shr => String::from("SHR"),
             Operator::Assign => String::from("ASSIGN"),
         }
     }
@@ -422,6 +520,8 @@ impl PrettyPrint for Type {
         match self {
             Type::Int => String::from("int"),
             Type::Char => String::from("char"),
+            Type::IntArray(length) => format!("int[{}]", length),
+            Type::CharArray(length) => format!("char[{}]", length),
         }
     }
 
@@ -6,6 +6,7 @@ use std::fmt::Display;
 
 use crate::{
     context::Context,
+    diagnostic::Coded,
     scanner::{error::Error as ScannerError, token::Token},
 };
 
@@ -54,6 +55,15 @@ impl Display for Error {
     }
 }
 
+impl Coded for Error {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::SyntaxError { .. } => "T0201",
+            Self::ScannerError(e) => e.code(),
+        }
+    }
+}
+
 impl From<ScannerError> for Error {
     fn from(value: ScannerError) -> Self {
         Self::ScannerError(value)
@@ -8,15 +8,15 @@
 use crate::{
     context::Context,
     scanner::{
+        error::Warning,
         token::{
             AddOp::*,
             Keyword::*,
-            MulOp::*,
-            RelOp::*,
             Token::{self, *},
         },
         Scanner,
     },
+    span::{Span, Spanned},
 };
 
 pub mod error;
@@ -25,12 +25,28 @@ use error::Error;
 pub mod ast;
 use ast::*;
 
+mod precedence;
+use precedence::{InfixKind, Precedence};
+
 /// Short-hand version of [Result], where E = [Context]
 type Result<T> = core::result::Result<T, Context<Error>>;
 
+/// Everything found by parsing a file
+pub struct Parsed {
+    /// The AST, which is missing the parts of the program that could not be parsed
+    pub program: Program,
+    /// Errors from the scanner and parser, in the order they were found
+    pub errors: Vec<Context<Error>>,
+    /// Warnings from the scanner, in the order they were found
+    pub warnings: Vec<Context<Warning>>,
+}
+
 /// Parser implementation, which consumes the scanner iterator.
 ///
-/// This is implemented as an LL(1) recursive descent predictive parser.
+/// This is implemented as an LL(1) recursive descent predictive parser, except for binary
+/// operators, which are parsed by precedence climbing over one table. Syntax errors are
+/// recovered from in panic mode: the error is recorded, tokens are skipped up to a point
+/// where parsing can carry on, and the broken part of the program is left out of the AST.
 pub struct Parser {
     /// The inner Scanner iterator
     scanner: Scanner,
@@ -39,7 +55,11 @@ pub struct Parser {
     /// Whether or not to print verbose information
     _verbose: bool,
     /// Look-ahead buffer
-    buffer: Token,
+    buffer: Spanned<Token>,
+    /// Span of the last token taken out of the buffer
+    previous: Span,
+    /// Errors found so far, in the order they were found
+    errors: Vec<Context<Error>>,
 }
 
 impl Parser {
@@ -52,21 +72,32 @@ impl Parser {
     pub fn new(mut scanner: Scanner, debug: bool, verbose: bool) -> Result<Self> {
         let token = scanner.next().unwrap()?;
 
-        Ok(Self {
+        let mut parser = Self {
             scanner,
             debug,
             _verbose: verbose,
             buffer: token,
-        })
+            previous: Span::default(),
+            errors: Vec::new(),
+        };
+        parser.collect_scanner_errors();
+
+        Ok(parser)
     }
 
     /// Parse into an AST, consuming the parser
     ///
-    /// # Errors
-    ///
-    /// Errors can happen during scanning, I/O, or because of syntax errors in the input file.
-    pub fn parse(mut self) -> Result<Program> {
-        self.nt_toy_c_program()
+    /// Along with the AST, this returns every error and warning found. Errors can happen during
+    /// scanning, I/O, or because of syntax errors in the input file. When there are errors, the
+    /// AST is missing the parts of the program that could not be parsed.
+    pub fn parse(mut self) -> Parsed {
+        let program = self.nt_toy_c_program();
+
+        Parsed {
+            program,
+            errors: self.errors,
+            warnings: self.scanner.take_warnings(),
+        }
     }
 
     /* Main implementation, based on the notes in class */
@@ -76,36 +107,93 @@ impl Parser {
     /// # Panics
     ///
     /// Panics if called after the EOF marker.
-    fn take_unchecked(&mut self) -> Result<Token> {
+    fn take_unchecked(&mut self) -> Result<Spanned<Token>> {
         let new_token = self.scanner.next().unwrap()?;
+        self.collect_scanner_errors();
 
         // swap in place to avoid cloning
         let old_token = std::mem::replace(&mut self.buffer, new_token);
+        self.previous = old_token.span;
 
         Ok(old_token)
     }
 
+    /// Records errors the scanner recovered from, which come before any syntax error in the
+    /// token that was just scanned
+    fn collect_scanner_errors(&mut self) {
+        let errors = self.scanner.take_errors();
+        self.errors.extend(errors.into_iter().map(Context::from));
+    }
+
     /// Same as [Self::take_unchecked], but also checks whether the token is the expected type.
     ///
     /// The justification here is that when there's only one path for the parser to take, this
     /// function can be used to simplify the logic. When there are multiple, the other can be used
     /// in a `match` statement.
-    fn take_checked(&mut self, expected: Token) -> Result<Token> {
-        if self.buffer.syntax_eq(&expected) {
+    fn take_checked(&mut self, expected: Token) -> Result<Spanned<Token>> {
+        if self.buffer.node.syntax_eq(&expected) {
             self.take_unchecked()
         } else {
             Err(self.expected(&[expected]))
         }
     }
 
+    /// Takes an identifier, keeping where it is so declarations can be reported
+    fn take_identifier(&mut self) -> Result<Spanned<ast::Identifier>> {
+        let Spanned { node, span } = self.take_checked(Identifier(String::new()))?;
+        Ok(Spanned::new(node.try_into().unwrap(), span))
+    }
+
     /// Constructs a syntax error
     fn expected(&self, expected: &[Token]) -> Context<Error> {
         self.scanner.context(Error::SyntaxError {
-            got: self.buffer.clone(),
+            got: self.buffer.node.clone(),
             expected: expected.to_owned(),
         })
     }
 
+    /// Records an error that parsing can carry on from.
+    ///
+    /// Scanner errors can't be recovered from, and neither can errors at the end of the file
+    /// since there is nothing left to parse, so these are passed on instead.
+    fn recover(&mut self, e: Context<Error>) -> Result<()> {
+        if matches!(e.kind(), Error::ScannerError(_)) || self.buffer.node == Eof {
+            return Err(e);
+        }
+
+        self.errors.push(e);
+        Ok(())
+    }
+
+    /// Skips tokens up to the end of the current statement, so that parsing can carry on from
+    /// the start of the next one. A `;` is taken, while tokens which may start or end a statement
+    /// are left in the buffer.
+    fn synchronize(&mut self) -> Result<()> {
+        loop {
+            match self.buffer.node {
+                Semicolon => {
+                    self.take_unchecked()?;
+                    return Ok(());
+                }
+                Keyword(
+                    Break | Case | Continue | Default | Do | Else | For | If | Newline | Read
+                    | Return | Switch | While | Write,
+                )
+                | LCurly
+                | RCurly
+                | Eof => return Ok(()),
+                _ => {
+                    self.take_unchecked()?;
+                }
+            }
+        }
+    }
+
+    /// Span from the start of a node's first token up to the last token taken
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.previous)
+    }
+
     /// Prints debug messages
     fn debug(&self, msg: &str) {
         if self.debug {
@@ -114,66 +202,99 @@ impl Parser {
     }
 
     /// `ToyCProgram' <EOF>`
-    fn nt_toy_c_program(&mut self) -> Result<Program> {
+    fn nt_toy_c_program(&mut self) -> Program {
         self.debug("entering ToyCProgram");
 
         let mut definitions = Vec::new();
-        self.nt_toy_c_program_(&mut definitions)?;
-
-        // don't call take because it will load the buffer after EOF, causing a panic
-        let res = match self.buffer {
-            Eof => Ok(Program(definitions)),
-            _ => Err(self.expected(&[Eof])),
-        }?;
+        match self.nt_toy_c_program_(&mut definitions) {
+            // don't call take because it will load the buffer after EOF, causing a panic
+            Ok(()) if self.buffer.node != Eof => self.errors.push(self.expected(&[Eof])),
+            Ok(()) => (),
+            // errors which can't be recovered from end parsing, keeping the definitions so far
+            Err(e) => self.errors.push(e),
+        }
+        let res = Program(definitions);
 
         self.debug("exiting ToyCProgram");
-        Ok(res)
+        res
     }
 
     /// `Definition ToyCProgram' | ε`
     fn nt_toy_c_program_(&mut self, definitions: &mut Vec<Definition>) -> Result<()> {
         self.debug("entering ToyCProgram'");
 
-        match self.buffer {
+        match self.buffer.node {
             Keyword(Int | Char) => {
-                let definition = self.nt_definition()?;
-                definitions.push(definition);
+                match self.nt_definition() {
+                    Ok(definition) => definitions.push(definition),
+                    Err(e) => {
+                        self.recover(e)?;
+                        self.synchronize_definition()?;
+                    }
+                }
                 self.nt_toy_c_program_(definitions)
             }
             Eof => Ok(()),
-            _ => Err(self.expected(&[Keyword(Int), Keyword(Char), Eof])),
+            _ => {
+                let e = self.expected(&[Keyword(Int), Keyword(Char), Eof]);
+                self.recover(e)?;
+                self.synchronize_definition()?;
+                self.nt_toy_c_program_(definitions)
+            }
         }?;
 
         self.debug("Exiting ToyCProgram'");
         Ok(())
     }
 
+    /// Skips tokens up to the start of the next definition, which is a type outside of any braces
+    fn synchronize_definition(&mut self) -> Result<()> {
+        let mut depth = 0usize;
+        loop {
+            match self.buffer.node {
+                Keyword(Int | Char) if depth == 0 => return Ok(()),
+                Eof => return Ok(()),
+                LCurly => depth += 1,
+                RCurly => depth = depth.saturating_sub(1),
+                _ => (),
+            }
+            self.take_unchecked()?;
+        }
+    }
+
     /// `Type <identifier> Definition'`
     fn nt_definition(&mut self) -> Result<Definition> {
         self.debug("entering Definition");
 
+        let start = self.buffer.span;
         let ast_type = self.nt_type()?;
-        let id = self
-            .take_checked(Identifier(String::new()))?
-            .try_into()
-            .unwrap();
-        let res = self.nt_definition_(ast_type, id)?;
+        let id = self.take_identifier()?;
+        let kind = self.nt_definition_(ast_type, id)?;
+        let res = Definition {
+            kind,
+            span: self.span_from(start),
+        };
 
         self.debug("exiting Definition");
         Ok(res)
     }
 
-    /// `FunctionDefinition | <;>`
-    fn nt_definition_(&mut self, ast_type: Type, id: String) -> Result<Definition> {
+    /// `FunctionDefinition | ArrayLength <;>`
+    fn nt_definition_(
+        &mut self,
+        ast_type: Type,
+        id: Spanned<ast::Identifier>,
+    ) -> Result<DefinitionKind> {
         self.debug("entering Definition'");
 
-        let res = match self.buffer {
-            LParen => self.nt_function_definition(ast_type, id),
-            Semicolon => {
-                self.take_unchecked()?;
-                Ok(Definition::Var(vec![id], ast_type))
+        let res = match self.buffer.node {
+            LParen => self.nt_function_definition(ast_type, id.node),
+            Semicolon | LBracket => {
+                let ast_type = self.nt_array_length(ast_type)?;
+                self.take_checked(Semicolon)?;
+                Ok(DefinitionKind::Var(vec![id], ast_type))
             }
-            _ => Err(self.expected(&[LParen, Semicolon])),
+            _ => Err(self.expected(&[LParen, LBracket, Semicolon])),
         }?;
 
         self.debug("exiting Definition'");
@@ -184,9 +305,9 @@ impl Parser {
     fn nt_type(&mut self) -> Result<Type> {
         self.debug("entering Type");
 
-        let res = match self.buffer {
+        let res = match self.buffer.node {
             Keyword(Int) | Keyword(Char) => {
-                let ast_type = self.take_unchecked()?.try_into().unwrap();
+                let ast_type = self.take_unchecked()?.node.try_into().unwrap();
                 Ok(ast_type)
             }
             _ => Err(self.expected(&[Keyword(Int), Keyword(Char)])),
@@ -197,12 +318,12 @@ impl Parser {
     }
 
     /// `FunctionHeader FunctionBody`
-    fn nt_function_definition(&mut self, ast_type: Type, id: String) -> Result<Definition> {
+    fn nt_function_definition(&mut self, ast_type: Type, id: String) -> Result<DefinitionKind> {
         self.debug("entering FunctionDefinition");
 
         let var_def = self.nt_function_header()?;
         let statement = self.nt_function_body()?;
-        let res = Definition::Func(id, ast_type, var_def, statement);
+        let res = DefinitionKind::Func(id, ast_type, var_def, statement);
 
         self.debug("exiting FunctionDefinition");
         Ok(res)
@@ -224,7 +345,7 @@ impl Parser {
     fn nt_function_header_(&mut self) -> Result<Vec<VarDef>> {
         self.debug("entering FunctionHeader'");
 
-        let res = match self.buffer {
+        let res = match self.buffer.node {
             Keyword(Int) | Keyword(Char) => self.nt_formal_param_list(),
             RParen => Ok(Vec::new()),
             _ => Err(self.expected(&[Keyword(Int), Keyword(Char), RParen])),
@@ -238,7 +359,12 @@ impl Parser {
     fn nt_function_body(&mut self) -> Result<Statement> {
         self.debug("entering FunctionBody");
 
-        let res = self.nt_compound_statement()?;
+        let start = self.buffer.span;
+        let kind = self.nt_compound_statement()?;
+        let res = Statement {
+            kind,
+            span: self.span_from(start),
+        };
 
         self.debug("exiting FunctionBody");
         Ok(res)
@@ -248,12 +374,10 @@ impl Parser {
     fn nt_formal_param_list(&mut self) -> Result<Vec<VarDef>> {
         self.debug("entering FormalParamList");
 
+        let start = self.buffer.span;
         let ast_type = self.nt_type()?;
-        let id = self
-            .take_checked(Identifier(String::new()))?
-            .try_into()
-            .unwrap();
-        let mut res = vec![(vec![id], ast_type)];
+        let id = self.take_identifier()?;
+        let mut res = vec![(vec![id], ast_type, self.span_from(start))];
         self.nt_formal_param_list_(&mut res)?;
 
         self.debug("exiting FormalParamList");
@@ -264,15 +388,13 @@ impl Parser {
     fn nt_formal_param_list_(&mut self, var_def: &mut Vec<VarDef>) -> Result<()> {
         self.debug("entering FormalParamList'");
 
-        match self.buffer {
+        match self.buffer.node {
             Comma => {
                 self.take_unchecked()?;
+                let start = self.buffer.span;
                 let ast_type = self.nt_type()?;
-                let id = self
-                    .take_checked(Identifier(String::new()))?
-                    .try_into()
-                    .unwrap();
-                var_def.push((vec![id], ast_type));
+                let id = self.take_identifier()?;
+                var_def.push((vec![id], ast_type, self.span_from(start)));
                 self.nt_formal_param_list_(var_def)
             }
             RParen => Ok(()),
@@ -285,80 +407,120 @@ impl Parser {
 
     /// `ExpressionStatement
     ///  | BreakStatement
+    ///  | ContinueStatement
     ///  | CompoundStatement
     ///  | IfStatement
     ///  | NullStatement
     ///  | ReturnStatement
     ///  | WhileStatement
+    ///  | DoStatement
+    ///  | ForStatement
+    ///  | SwitchStatement
     ///  | ReadStatement
     ///  | WriteStatement
     ///  | NewLineStatement`
     fn nt_statement(&mut self) -> Result<Statement> {
         self.debug("entering Statement");
 
-        let res = match self.buffer {
-            Identifier(_) | Number(_) | LParen | Not | CharLiteral(_) | StringLiteral(_)
-            | AddOp(Sub) => self.nt_expression_statement(),
+        let start = self.buffer.span;
+        let kind = match self.buffer.node {
+            Identifier(_) | Number(_) | LParen | Not | BitNot | CharLiteral(_)
+            | StringLiteral(_) | AddOp(Sub) => self.nt_expression_statement(),
             Keyword(Break) => self.nt_break_statement(),
+            Keyword(Continue) => self.nt_continue_statement(),
             LCurly => self.nt_compound_statement(),
             Keyword(If) => self.nt_if_statement(),
             Semicolon => self.nt_null_statement(),
             Keyword(Return) => self.nt_return_statement(),
             Keyword(While) => self.nt_while_statement(),
+            Keyword(Do) => self.nt_do_statement(),
+            Keyword(For) => self.nt_for_statement(),
+            Keyword(Switch) => self.nt_switch_statement(),
             Keyword(Read) => self.nt_read_statement(),
             Keyword(Write) => self.nt_write_statement(),
             Keyword(Newline) => self.nt_newline_statement(),
 
             _ => Err(self.expected(&[
                 Identifier(String::new()),
-                Number(String::new()),
+                Number(0),
                 LParen,
                 Not,
-                CharLiteral(None),
+                BitNot,
+                CharLiteral('\0'),
                 StringLiteral(String::new()),
                 AddOp(Sub),
                 Keyword(Break),
+                Keyword(Continue),
                 LCurly,
                 Keyword(If),
                 Semicolon,
                 Keyword(Return),
                 Keyword(While),
+                Keyword(Do),
+                Keyword(For),
+                Keyword(Switch),
                 Keyword(Read),
                 Keyword(Write),
                 Keyword(Newline),
             ])),
-        }?;
+        };
+
+        // a statement with errors is left out, but is replaced to keep the rest of the AST
+        let kind = match kind {
+            Ok(kind) => kind,
+            Err(e) => {
+                self.recover(e)?;
+                self.synchronize()?;
+                StatementKind::Null
+            }
+        };
+        let res = Statement {
+            kind,
+            span: self.span_from(start),
+        };
 
         self.debug("exiting Statement");
         Ok(res)
     }
 
     /// `Expression <;>`
-    fn nt_expression_statement(&mut self) -> Result<Statement> {
+    fn nt_expression_statement(&mut self) -> Result<StatementKind> {
         self.debug("entering ExpressionStatement");
 
         let expression = self.nt_expression()?;
         self.take_checked(Semicolon)?;
-        let res = Statement::Expr(expression);
+        let res = StatementKind::Expr(expression);
 
         self.debug("exiting ExpressionStatement");
         Ok(res)
     }
 
     /// `<break> <;>`
-    fn nt_break_statement(&mut self) -> Result<Statement> {
+    fn nt_break_statement(&mut self) -> Result<StatementKind> {
         self.debug("entering BreakStatement");
 
         self.take_checked(Keyword(Break))?;
         self.take_checked(Semicolon)?;
-        let res = Statement::Break;
+        let res = StatementKind::Break;
 
         self.debug("exiting BreakStatement");
         Ok(res)
     }
 
+    /// `<continue> <;>`
+    fn nt_continue_statement(&mut self) -> Result<StatementKind> {
+        self.debug("entering ContinueStatement");
+
+        self.take_checked(Keyword(Continue))?;
+        self.take_checked(Semicolon)?;
+        let res = StatementKind::Continue;
+
+        self.debug("exiting ContinueStatement");
+        Ok(res)
+    }
+
     /// `<{> CompoundStatement' CompoundStatement'' <}>`
-    fn nt_compound_statement(&mut self) -> Result<Statement> {
+    fn nt_compound_statement(&mut self) -> Result<StatementKind> {
         self.debug("entering CompoundStatement");
 
         self.take_checked(LCurly)?;
@@ -367,7 +529,7 @@ impl Parser {
         let mut statements = Vec::new();
         self.nt_compound_statement__(&mut statements)?;
         self.take_checked(RCurly)?;
-        let res = Statement::Block(var_def, statements);
+        let res = StatementKind::Block(var_def, statements);
 
         self.debug("exiting CompoundStatement");
         Ok(res)
@@ -377,18 +539,20 @@ impl Parser {
     fn nt_compound_statement_(&mut self, var_def: &mut Vec<VarDef>) -> Result<()> {
         self.debug("entering CompoundStatement'");
 
-        match self.buffer {
+        match self.buffer.node {
             Keyword(Int | Char) => {
-                let ast_type = self.nt_type()?;
-                let id = self
-                    .take_checked(Identifier(String::new()))?
-                    .try_into()
-                    .unwrap();
-                var_def.push((vec![id], ast_type));
-                self.take_checked(Semicolon)?;
+                match self.variable_declaration() {
+                    Ok(declaration) => var_def.push(declaration),
+                    Err(e) => {
+                        self.recover(e)?;
+                        self.synchronize()?;
+                    }
+                }
                 self.nt_compound_statement_(var_def)
             }
-            Keyword(Read | Newline | Write | While | Break | Return | If)
+            Keyword(
+                Read | Newline | Write | While | Do | For | Switch | Break | Continue | Return | If,
+            )
             | Identifier(_)
             | Number(_)
             | StringLiteral(_)
@@ -397,41 +561,88 @@ impl Parser {
             | LCurly
             | RCurly
             | Not
+            | BitNot
             | Semicolon
             | LParen => Ok(()),
-            _ => Err(self.expected(&[
-                Keyword(Int),
-                Keyword(Char),
-                Keyword(Read),
-                Keyword(Newline),
-                Keyword(Write),
-                Keyword(While),
-                Keyword(Break),
-                Keyword(Return),
-                Keyword(If),
-                Identifier(String::new()),
-                Number(String::new()),
-                StringLiteral(String::new()),
-                CharLiteral(None),
-                AddOp(Sub),
-                LCurly,
-                RCurly,
-                Not,
-                Semicolon,
-                LParen,
-            ])),
+            _ => {
+                let e = self.expected(&[
+                    Keyword(Int),
+                    Keyword(Char),
+                    Keyword(Read),
+                    Keyword(Newline),
+                    Keyword(Write),
+                    Keyword(While),
+                    Keyword(Do),
+                    Keyword(For),
+                    Keyword(Switch),
+                    Keyword(Break),
+                    Keyword(Continue),
+                    Keyword(Return),
+                    Keyword(If),
+                    Identifier(String::new()),
+                    Number(0),
+                    StringLiteral(String::new()),
+                    CharLiteral('\0'),
+                    AddOp(Sub),
+                    LCurly,
+                    RCurly,
+                    Not,
+                    BitNot,
+                    Semicolon,
+                    LParen,
+                ]);
+                self.recover(e)?;
+                // the token can't start a declaration or statement, so it is skipped
+                self.take_unchecked()?;
+                self.nt_compound_statement_(var_def)
+            }
         }?;
 
         self.debug("exiting CompoundStatement'");
         Ok(())
     }
 
+    /// `Type <identifier> ArrayLength <;>`, a single declaration from CompoundStatement'
+    fn variable_declaration(&mut self) -> Result<VarDef> {
+        let start = self.buffer.span;
+        let ast_type = self.nt_type()?;
+        let id = self.take_identifier()?;
+        let ast_type = self.nt_array_length(ast_type)?;
+        self.take_checked(Semicolon)?;
+
+        Ok((vec![id], ast_type, self.span_from(start)))
+    }
+
+    /// `<[> <number> <]> | ε`, making the declared type an array when there is a length
+    fn nt_array_length(&mut self, ast_type: Type) -> Result<Type> {
+        self.debug("entering ArrayLength");
+
+        let res = match self.buffer.node {
+            LBracket => {
+                self.take_unchecked()?;
+                let Number(length) = self.take_checked(Number(0))?.node else {
+                    unreachable!()
+                };
+                self.take_checked(RBracket)?;
+
+                Ok(ast_type.array(length))
+            }
+            Semicolon => Ok(ast_type),
+            _ => Err(self.expected(&[LBracket, Semicolon])),
+        }?;
+
+        self.debug("exiting ArrayLength");
+        Ok(res)
+    }
+
     /// `Statement CompoundStatement'' | ε`
     fn nt_compound_statement__(&mut self, statements: &mut Vec<Statement>) -> Result<()> {
         self.debug("entering CompoundStatement''");
 
-        match self.buffer {
-            Keyword(Read | Newline | Write | While | Break | Return | If)
+        match self.buffer.node {
+            Keyword(
+                Read | Newline | Write | While | Do | For | Switch | Break | Continue | Return | If,
+            )
             | Identifier(_)
             | Number(_)
             | StringLiteral(_)
@@ -439,6 +650,7 @@ impl Parser {
             | AddOp(Sub)
             | LCurly
             | Not
+            | BitNot
             | Semicolon
             | LParen => {
                 let statement = self.nt_statement()?;
@@ -446,25 +658,36 @@ impl Parser {
                 self.nt_compound_statement__(statements)
             }
             RCurly => Ok(()),
-            _ => Err(self.expected(&[
-                Keyword(Read),
-                Keyword(Newline),
-                Keyword(Write),
-                Keyword(While),
-                Keyword(Break),
-                Keyword(Return),
-                Keyword(If),
-                Identifier(String::new()),
-                Number(String::new()),
-                StringLiteral(String::new()),
-                CharLiteral(None),
-                AddOp(Sub),
-                LCurly,
-                RCurly,
-                Not,
-                Semicolon,
-                LParen,
-            ])),
+            _ => {
+                let e = self.expected(&[
+                    Keyword(Read),
+                    Keyword(Newline),
+                    Keyword(Write),
+                    Keyword(While),
+                    Keyword(Do),
+                    Keyword(For),
+                    Keyword(Switch),
+                    Keyword(Break),
+                    Keyword(Continue),
+                    Keyword(Return),
+                    Keyword(If),
+                    Identifier(String::new()),
+                    Number(0),
+                    StringLiteral(String::new()),
+                    CharLiteral('\0'),
+                    AddOp(Sub),
+                    LCurly,
+                    RCurly,
+                    Not,
+                    BitNot,
+                    Semicolon,
+                    LParen,
+                ]);
+                self.recover(e)?;
+                // the token can't start a statement, so it is skipped
+                self.take_unchecked()?;
+                self.nt_compound_statement__(statements)
+            }
         }?;
 
         self.debug("exiting CompoundStatement''");
@@ -472,7 +695,7 @@ impl Parser {
     }
 
     /// `<if> <(> Expression <)> Statement IfStatement'`
-    fn nt_if_statement(&mut self) -> Result<Statement> {
+    fn nt_if_statement(&mut self) -> Result<StatementKind> {
         self.debug("entering IfStatement");
 
         self.take_checked(Keyword(If))?;
@@ -481,7 +704,7 @@ impl Parser {
         self.take_checked(RParen)?;
         let true_statement = Box::new(self.nt_statement()?);
         let false_statement = self.nt_if_statement_()?.map(Box::new);
-        let res = Statement::If(expression, true_statement, false_statement);
+        let res = StatementKind::If(expression, true_statement, false_statement);
 
         self.debug("exiting IfStatement");
         Ok(res)
@@ -491,14 +714,16 @@ impl Parser {
     fn nt_if_statement_(&mut self) -> Result<Option<Statement>> {
         self.debug("entering IfStatement'");
 
-        let res = match self.buffer {
+        let res = match self.buffer.node {
             Keyword(Else) => {
                 self.take_checked(Keyword(Else))?;
                 let statement = self.nt_statement()?;
 
                 Ok(Some(statement))
             }
-            Keyword(Read | Newline | Write | While | Break | Return | If)
+            Keyword(
+                Read | Newline | Write | While | Do | For | Switch | Break | Continue | Return | If,
+            )
             | Identifier(_)
             | Number(_)
             | StringLiteral(_)
@@ -506,25 +731,35 @@ impl Parser {
             | AddOp(Sub)
             | LCurly
             | Not
+            | BitNot
+            | RCurly
             | Semicolon
-            | LParen => Ok(None),
+            | LParen
+            | Keyword(Case | Default) => Ok(None),
             _ => Err(self.expected(&[
                 Keyword(Read),
                 Keyword(Newline),
                 Keyword(Write),
                 Keyword(While),
+                Keyword(Do),
+                Keyword(For),
+                Keyword(Switch),
                 Keyword(Break),
+                Keyword(Continue),
                 Keyword(Return),
                 Keyword(If),
                 Keyword(Else),
+                Keyword(Case),
+                Keyword(Default),
                 Identifier(String::new()),
-                Number(String::new()),
+                Number(0),
                 StringLiteral(String::new()),
-                CharLiteral(None),
+                CharLiteral('\0'),
                 AddOp(Sub),
                 LCurly,
                 RCurly,
                 Not,
+                BitNot,
                 Semicolon,
                 LParen,
             ])),
@@ -535,24 +770,24 @@ impl Parser {
     }
 
     /// `<;>`
-    fn nt_null_statement(&mut self) -> Result<Statement> {
+    fn nt_null_statement(&mut self) -> Result<StatementKind> {
         self.debug("entering NullStatement");
 
         self.take_checked(Semicolon)?;
-        let res = Statement::Null;
+        let res = StatementKind::Null;
 
         self.debug("exitingNullStatement");
         Ok(res)
     }
 
     /// `<return> ReturnStatement' <;>`
-    fn nt_return_statement(&mut self) -> Result<Statement> {
+    fn nt_return_statement(&mut self) -> Result<StatementKind> {
         self.debug("entering ReturnStatement");
 
         self.take_checked(Keyword(Return))?;
         let expression = self.nt_return_statement_()?;
         self.take_checked(Semicolon)?;
-        let res = Statement::Return(expression);
+        let res = StatementKind::Return(expression);
 
         self.debug("exiting ReturnStatement");
         Ok(res)
@@ -562,8 +797,8 @@ impl Parser {
     fn nt_return_statement_(&mut self) -> Result<Option<Expression>> {
         self.debug("entering ReturnStatement'");
 
-        let res = match self.buffer {
-            AddOp(Sub) | LParen | StringLiteral(_) | CharLiteral(_) | Number(_) | Not
+        let res = match self.buffer.node {
+            AddOp(Sub) | LParen | StringLiteral(_) | CharLiteral(_) | Number(_) | Not | BitNot
             | Identifier(_) => {
                 let expression = self.nt_expression()?;
 
@@ -574,10 +809,12 @@ impl Parser {
                 AddOp(Sub),
                 LParen,
                 StringLiteral(String::new()),
-                CharLiteral(None),
-                Number(String::new()),
+                CharLiteral('\0'),
+                Number(0),
                 Not,
+                BitNot,
                 Identifier(String::new()),
+                Semicolon,
             ])),
         }?;
 
@@ -586,7 +823,7 @@ impl Parser {
     }
 
     /// `<while> <(> Expression <)> Statement`
-    fn nt_while_statement(&mut self) -> Result<Statement> {
+    fn nt_while_statement(&mut self) -> Result<StatementKind> {
         self.debug("entering WhileStatement");
 
         self.take_checked(Keyword(While))?;
@@ -594,43 +831,244 @@ impl Parser {
         let expression = self.nt_expression()?;
         self.take_checked(RParen)?;
         let statement = Box::new(self.nt_statement()?);
-        let res = Statement::While(expression, statement);
+        let res = StatementKind::While(expression, statement);
 
         self.debug("exiting WhileStatement");
         Ok(res)
     }
 
+    /// `<do> Statement <while> <(> Expression <)> <;>`
+    fn nt_do_statement(&mut self) -> Result<StatementKind> {
+        self.debug("entering DoStatement");
+
+        self.take_checked(Keyword(Do))?;
+        let statement = Box::new(self.nt_statement()?);
+        self.take_checked(Keyword(While))?;
+        self.take_checked(LParen)?;
+        let expression = self.nt_expression()?;
+        self.take_checked(RParen)?;
+        self.take_checked(Semicolon)?;
+        let res = StatementKind::DoWhile(statement, expression);
+
+        self.debug("exiting DoStatement");
+        Ok(res)
+    }
+
+    /// `<for> <(> ForStatement' <;> ForStatement' <;> ForStatement' <)> Statement`
+    fn nt_for_statement(&mut self) -> Result<StatementKind> {
+        self.debug("entering ForStatement");
+
+        self.take_checked(Keyword(For))?;
+        self.take_checked(LParen)?;
+        let init = self.nt_for_statement_(Semicolon)?.map(Box::new);
+        self.take_checked(Semicolon)?;
+        let condition = self.nt_for_statement_(Semicolon)?.map(Box::new);
+        self.take_checked(Semicolon)?;
+        let step = self.nt_for_statement_(RParen)?.map(Box::new);
+        self.take_checked(RParen)?;
+        let statement = Box::new(self.nt_statement()?);
+        let res = StatementKind::For(init, condition, step, statement);
+
+        self.debug("exiting ForStatement");
+        Ok(res)
+    }
+
+    /// `Expression | ε`, where `follow` is the token after the ε
+    fn nt_for_statement_(&mut self, follow: Token) -> Result<Option<Expression>> {
+        self.debug("entering ForStatement'");
+
+        let res = match self.buffer.node {
+            AddOp(Sub) | LParen | StringLiteral(_) | CharLiteral(_) | Number(_) | Not | BitNot
+            | Identifier(_) => {
+                let expression = self.nt_expression()?;
+
+                Ok(Some(expression))
+            }
+            _ if self.buffer.node.syntax_eq(&follow) => Ok(None),
+            _ => Err(self.expected(&[
+                AddOp(Sub),
+                LParen,
+                StringLiteral(String::new()),
+                CharLiteral('\0'),
+                Number(0),
+                Not,
+                BitNot,
+                Identifier(String::new()),
+                follow,
+            ])),
+        }?;
+
+        self.debug("exiting ForStatement'");
+        Ok(res)
+    }
+
+    /// `<switch> <(> Expression <)> <{> SwitchStatement' <}>`
+    fn nt_switch_statement(&mut self) -> Result<StatementKind> {
+        self.debug("entering SwitchStatement");
+
+        self.take_checked(Keyword(Switch))?;
+        self.take_checked(LParen)?;
+        let expression = self.nt_expression()?;
+        self.take_checked(RParen)?;
+        self.take_checked(LCurly)?;
+        let mut cases = Vec::new();
+        self.nt_switch_statement_(&mut cases)?;
+        self.take_checked(RCurly)?;
+        let res = StatementKind::Switch(expression, cases);
+
+        self.debug("exiting SwitchStatement");
+        Ok(res)
+    }
+
+    /// `SwitchCase SwitchStatement' | ε`
+    fn nt_switch_statement_(&mut self, cases: &mut Vec<SwitchCase>) -> Result<()> {
+        self.debug("entering SwitchStatement'");
+
+        match self.buffer.node {
+            Keyword(Case | Default) => {
+                match self.nt_switch_case() {
+                    Ok(case) => cases.push(case),
+                    Err(e) => {
+                        self.recover(e)?;
+                        // the broken case is left out, along with its statements
+                        self.synchronize()?;
+                        self.nt_switch_case_(&mut Vec::new())?;
+                    }
+                }
+                self.nt_switch_statement_(cases)
+            }
+            RCurly => Ok(()),
+            _ => {
+                let e = self.expected(&[Keyword(Case), Keyword(Default), RCurly]);
+                self.recover(e)?;
+                // statements before the first case can never run, so they are left out
+                self.nt_switch_case_(&mut Vec::new())?;
+                self.nt_switch_statement_(cases)
+            }
+        }?;
+
+        self.debug("exiting SwitchStatement'");
+        Ok(())
+    }
+
+    /// `<case> Expression <:> SwitchCase' | <default> <:> SwitchCase'`
+    ///
+    /// The value of a case must be constant, which is checked by semantic analysis.
+    fn nt_switch_case(&mut self) -> Result<SwitchCase> {
+        self.debug("entering SwitchCase");
+
+        let start = self.buffer.span;
+        let value = match self.buffer.node {
+            Keyword(Case) => {
+                self.take_unchecked()?;
+                Ok(Some(self.nt_expression()?))
+            }
+            Keyword(Default) => {
+                self.take_unchecked()?;
+                Ok(None)
+            }
+            _ => Err(self.expected(&[Keyword(Case), Keyword(Default)])),
+        }?;
+        self.take_checked(Colon)?;
+        let span = self.span_from(start);
+        let mut statements = Vec::new();
+        self.nt_switch_case_(&mut statements)?;
+        let res = SwitchCase {
+            value,
+            statements,
+            span,
+        };
+
+        self.debug("exiting SwitchCase");
+        Ok(res)
+    }
+
+    /// `Statement SwitchCase' | ε`
+    fn nt_switch_case_(&mut self, statements: &mut Vec<Statement>) -> Result<()> {
+        self.debug("entering SwitchCase'");
+
+        match self.buffer.node {
+            Keyword(
+                Read | Newline | Write | While | Do | For | Switch | Break | Continue | Return | If,
+            )
+            | Identifier(_)
+            | Number(_)
+            | StringLiteral(_)
+            | CharLiteral(_)
+            | AddOp(Sub)
+            | LCurly
+            | Not
+            | BitNot
+            | Semicolon
+            | LParen => {
+                let statement = self.nt_statement()?;
+                statements.push(statement);
+                self.nt_switch_case_(statements)
+            }
+            Keyword(Case | Default) | RCurly => Ok(()),
+            _ => {
+                let e = self.expected(&[
+                    Keyword(Read),
+                    Keyword(Newline),
+                    Keyword(Write),
+                    Keyword(While),
+                    Keyword(Do),
+                    Keyword(For),
+                    Keyword(Switch),
+                    Keyword(Break),
+                    Keyword(Continue),
+                    Keyword(Return),
+                    Keyword(If),
+                    Keyword(Case),
+                    Keyword(Default),
+                    Identifier(String::new()),
+                    Number(0),
+                    StringLiteral(String::new()),
+                    CharLiteral('\0'),
+                    AddOp(Sub),
+                    LCurly,
+                    RCurly,
+                    Not,
+                    BitNot,
+                    Semicolon,
+                    LParen,
+                ]);
+                self.recover(e)?;
+                // the token can't start a statement, so it is skipped
+                self.take_unchecked()?;
+                self.nt_switch_case_(statements)
+            }
+        }?;
+
+        self.debug("exiting SwitchCase'");
+        Ok(())
+    }
+
     /// `<read> <(> <identifier> ReadStatement' <)> <;>`
-    fn nt_read_statement(&mut self) -> Result<Statement> {
+    fn nt_read_statement(&mut self) -> Result<StatementKind> {
         self.debug("entering ReadStatement");
 
         self.take_checked(Keyword(Read))?;
         self.take_checked(LParen)?;
-        let id = self
-            .take_checked(Identifier(String::new()))?
-            .try_into()
-            .unwrap();
+        let id = self.take_identifier()?;
         let mut ids = vec![id];
         self.nt_read_statement_(&mut ids)?;
         self.take_checked(RParen)?;
         self.take_checked(Semicolon)?;
-        let res = Statement::Read(ids);
+        let res = StatementKind::Read(ids);
 
         self.debug("exiting ReadStatement");
         Ok(res)
     }
 
     /// `<,> <identifier> ReadStatement' | ε`
-    fn nt_read_statement_(&mut self, ids: &mut Vec<String>) -> Result<()> {
+    fn nt_read_statement_(&mut self, ids: &mut Vec<Spanned<ast::Identifier>>) -> Result<()> {
         self.debug("entering ReadStatement'");
 
-        match self.buffer {
+        match self.buffer.node {
             Comma => {
                 self.take_unchecked()?;
-                let id = self
-                    .take_checked(Identifier(String::new()))?
-                    .try_into()
-                    .unwrap();
+                let id = self.take_identifier()?;
                 ids.push(id);
                 self.nt_read_statement_(ids)
             }
@@ -643,7 +1081,7 @@ impl Parser {
     }
 
     /// `<write> <(> ActualParameters <)> <;>`
-    fn nt_write_statement(&mut self) -> Result<Statement> {
+    fn nt_write_statement(&mut self) -> Result<StatementKind> {
         self.debug("entering WriteStatement");
 
         self.take_checked(Keyword(Write))?;
@@ -651,45 +1089,48 @@ impl Parser {
         let params = self.nt_actual_parameters()?;
         self.take_checked(RParen)?;
         self.take_checked(Semicolon)?;
-        let res = Statement::Write(params);
+        let res = StatementKind::Write(params);
 
         self.debug("exiting WriteStatement");
         Ok(res)
     }
 
     /// `<newline> <;>`
-    fn nt_newline_statement(&mut self) -> Result<Statement> {
+    fn nt_newline_statement(&mut self) -> Result<StatementKind> {
         self.debug("entering NewlineStatement");
 
         self.take_checked(Keyword(Newline))?;
         self.take_checked(Semicolon)?;
-        let res = Statement::Newline;
+        let res = StatementKind::Newline;
 
         self.debug("exiting NewlineStatement");
         Ok(res)
     }
 
-    /// `RelopExpression Expression'`
+    /// `BinaryExpression`, starting at the loosest operator
     fn nt_expression(&mut self) -> Result<Expression> {
         self.debug("entering Expression");
 
-        let res = match self.buffer {
-            Not | CharLiteral(_) | Number(_) | AddOp(_) | LParen | Identifier(_)
+        let res = match self.buffer.node {
+            Not | BitNot | CharLiteral(_) | Number(_) | AddOp(Sub) | LParen | Identifier(_)
             | StringLiteral(_) => {
-                let lhs = self.nt_relop_expression()?;
-                self.nt_expression_(lhs)
+                let exp = self.nt_binary_expression(Precedence::Assignment)?;
+
+                // every operator has been taken, so this must be the end of the expression
+                match self.buffer.node {
+                    Semicolon | Comma | RParen | RBracket | Colon => Ok(exp),
+                    _ => Err(self.expected(&Self::operand_follow())),
+                }
             }
             _ => Err(self.expected(&[
                 LParen,
                 Not,
-                LParen,
-                CharLiteral(None),
+                BitNot,
+                CharLiteral('\0'),
                 StringLiteral(String::new()),
                 Identifier(String::new()),
-                Number(String::new()),
-                AddOp(Add),
+                Number(0),
                 AddOp(Sub),
-                AddOp(BoolOr),
             ])),
         }?;
 
@@ -697,204 +1138,60 @@ impl Parser {
         Ok(res)
     }
 
-    /// `<assignop> RelopExpression Expression' | ε`
-    fn nt_expression_(&mut self, lhs: Expression) -> Result<Expression> {
-        self.debug("entering Expression'");
-
-        let res = match self.buffer {
-            AssignOp => {
-                self.take_unchecked()?;
-                let rhs = self.nt_relop_expression()?;
-                let exp = Expression::Expr(Operator::Assign, Box::new(lhs), Box::new(rhs));
-                self.nt_expression_(exp)
-            }
-            Semicolon | RParen | Comma => Ok(lhs),
-            _ => Err(self.expected(&[Semicolon, Comma, AssignOp, RParen])),
-        }?;
-
-        self.debug("exiting Expression'");
-        Ok(res)
-    }
-
-    /// `SimpleExpression RelopExpression'`
-    fn nt_relop_expression(&mut self) -> Result<Expression> {
-        self.debug("entering RelopExpression");
-
-        let res = match self.buffer {
-            AddOp(_) | StringLiteral(_) | CharLiteral(_) | Not | Identifier(_) | Number(_)
-            | LParen => {
-                let lhs = self.nt_simple_expression()?;
-                self.nt_relop_expression_(lhs)
-            }
-            _ => Err(self.expected(&[
-                AddOp(Sub),
-                AddOp(Add),
-                AddOp(BoolOr),
-                StringLiteral(String::new()),
-                CharLiteral(None),
-                Not,
-                Identifier(String::new()),
-                Number(String::new()),
-                LParen,
-            ])),
-        }?;
-
-        self.debug("exiting RelopExpression");
-        Ok(res)
-    }
-
-    /// `<relop> SimpleExpression RelopExpression' | ε`
-    fn nt_relop_expression_(&mut self, lhs: Expression) -> Result<Expression> {
-        self.debug("entering RelopExpression'");
-
-        let res = match self.buffer {
-            RelOp(_) => {
-                let op = self.take_unchecked()?.try_into().unwrap();
-                let rhs = self.nt_simple_expression()?;
-                let exp = Expression::Expr(op, Box::new(lhs), Box::new(rhs));
-                self.nt_relop_expression_(exp)
-            }
-            Semicolon | Comma | RParen | AssignOp => Ok(lhs),
-            _ => Err(self.expected(&[
-                AssignOp,
-                RParen,
-                RelOp(Gt),
-                RelOp(GtEq),
-                RelOp(Lt),
-                RelOp(LtEq),
-                RelOp(Eq),
-                RelOp(Neq),
-                Comma,
-                Semicolon,
-            ])),
-        }?;
-
-        self.debug("exiting RelopExpression'");
-        Ok(res)
-    }
-
-    /// `Term SimpleExpression'`
-    fn nt_simple_expression(&mut self) -> Result<Expression> {
-        self.debug("entering SimpleExpression");
-
-        let res = match self.buffer {
-            StringLiteral(_) | AddOp(_) | CharLiteral(_) | Number(_) | Identifier(_) | LParen
-            | Not => {
-                let lhs = self.nt_term()?;
-                self.nt_simple_expression_(lhs)
-            }
-            _ => Err(self.expected(&[
-                StringLiteral(String::new()),
-                AddOp(Sub),
-                AddOp(Add),
-                AddOp(BoolOr),
-                CharLiteral(None),
-                Number(String::new()),
-                Identifier(String::new()),
-                LParen,
-                Not,
-            ])),
-        }?;
-
-        self.debug("exiting SimpleExpression");
-        Ok(res)
-    }
+    /// `Primary (<infix> BinaryExpression)*`
+    ///
+    /// Only operators at least as tight as `min` are taken, which groups them by the table in
+    /// [precedence]. The right hand side of an operator only takes tighter operators, or ones
+    /// at the same level when the operator is right associative.
+    fn nt_binary_expression(&mut self, min: Precedence) -> Result<Expression> {
+        self.debug("entering BinaryExpression");
 
-    /// `<addop> Term SimpleExpression' | ε`
-    fn nt_simple_expression_(&mut self, lhs: Expression) -> Result<Expression> {
-        self.debug("entering SimpleExpression'");
+        let mut lhs = self.nt_primary()?;
 
-        let res = match self.buffer {
-            AddOp(_) => {
-                let op = self.take_unchecked()?.try_into().unwrap();
-                let rhs = self.nt_term()?;
-                let exp = Expression::Expr(op, Box::new(lhs), Box::new(rhs));
-                self.nt_relop_expression_(exp)
+        while let Some(infix) = precedence::infix(&self.buffer.node) {
+            if infix.precedence < min {
+                break;
             }
-            Semicolon | AssignOp | RelOp(_) | Comma | RParen => Ok(lhs),
-            _ => Err(self.expected(&[
-                AddOp(Sub),
-                AddOp(Add),
-                AddOp(BoolOr),
-                Semicolon,
-                AssignOp,
-                RelOp(Eq),
-                RelOp(Neq),
-                RelOp(Gt),
-                RelOp(GtEq),
-                RelOp(Lt),
-                RelOp(LtEq),
-                Comma,
-                RParen,
-            ])),
-        }?;
+            self.take_unchecked()?;
+
+            lhs = match infix.kind {
+                InfixKind::Binary(op) => {
+                    let rhs = self.nt_binary_expression(infix.rhs_precedence())?;
+                    Expression::binary(op, lhs, rhs)
+                }
+                InfixKind::Ternary => {
+                    let true_exp = self.nt_expression()?;
+                    self.take_checked(Colon)?;
+                    let false_exp = self.nt_binary_expression(infix.rhs_precedence())?;
+                    let span = lhs.span.to(false_exp.span);
+
+                    Expression::new(
+                        ExpressionKind::Conditional(
+                            Box::new(lhs),
+                            Box::new(true_exp),
+                            Box::new(false_exp),
+                        ),
+                        span,
+                    )
+                }
+            };
+        }
 
-        self.debug("exiting SimpleExpression'");
-        Ok(res)
+        self.debug("exiting BinaryExpression");
+        Ok(lhs)
     }
 
-    /// `Primary Term'`
-    fn nt_term(&mut self) -> Result<Expression> {
-        self.debug("entering Term");
-
-        let res = match self.buffer {
-            StringLiteral(_) | CharLiteral(_) | LParen | AddOp(_) | Number(_) | Not
-            | Identifier(_) => {
-                let lhs = self.nt_primary()?;
-                self.nt_term_(lhs)
-            }
-            _ => Err(self.expected(&[
-                AddOp(Sub),
-                AddOp(Add),
-                AddOp(BoolOr),
-                StringLiteral(String::new()),
-                LParen,
-                Number(String::new()),
-                Not,
-                Identifier(String::new()),
-            ])),
-        }?;
-
-        self.debug("exiting Term");
-        Ok(res)
+    /// Whether a token may come after an operand: an infix operator, or the end of an expression
+    fn ends_operand(token: &Token) -> bool {
+        matches!(token, Semicolon | Comma | RParen | RBracket | Colon)
+            || precedence::infix(token).is_some()
     }
 
-    /// `<mulop> Primary Term' | ε`
-    fn nt_term_(&mut self, lhs: Expression) -> Result<Expression> {
-        self.debug("entering Term'");
-
-        let res = match self.buffer {
-            MulOp(_) => {
-                let op = self.take_unchecked()?.try_into().unwrap();
-                let rhs = self.nt_primary()?;
-                let exp = Expression::Expr(op, Box::new(lhs), Box::new(rhs));
-                self.nt_term_(exp)
-            }
-            AddOp(_) | Comma | Semicolon | RParen | RelOp(_) | AssignOp => Ok(lhs),
-            _ => Err(self.expected(&[
-                MulOp(BoolAnd),
-                MulOp(Div),
-                MulOp(Mod),
-                MulOp(Mul),
-                AddOp(Sub),
-                AddOp(Add),
-                AddOp(BoolOr),
-                AddOp(BoolOr),
-                Comma,
-                Semicolon,
-                RParen,
-                RelOp(Gt),
-                RelOp(GtEq),
-                RelOp(Lt),
-                RelOp(LtEq),
-                RelOp(Eq),
-                RelOp(Neq),
-                AssignOp,
-            ])),
-        }?;
-
-        self.debug("exiting Term'");
-        Ok(res)
+    /// Tokens which may come after an operand, for syntax errors
+    fn operand_follow() -> Vec<Token> {
+        let mut follow: Vec<_> = precedence::operators().collect();
+        follow.extend([Semicolon, Comma, RParen, RBracket, Colon]);
+        follow
     }
 
     /// `Identifier Primary'
@@ -903,46 +1200,67 @@ impl Parser {
     /// | <CharConstant>
     /// | <(> Expression <)>
     /// | <-> Primary
-    /// | <Not> Primary`
+    /// | <Not> Primary
+    /// | <~> Primary`
     fn nt_primary(&mut self) -> Result<Expression> {
         self.debug("entering Primary");
 
-        let res = match &self.buffer {
+        let start = self.buffer.span;
+        let res = match &self.buffer.node {
             Identifier(_) => {
-                let id = self.take_unchecked()?.try_into().unwrap();
-                self.nt_primary_(id)
+                let id = self.take_unchecked()?.node.try_into().unwrap();
+                self.nt_primary_(id, start)
             }
             Number(_) | StringLiteral(_) | CharLiteral(_) => {
-                let exp = self.take_unchecked()?.try_into().unwrap();
+                let token = self.take_unchecked()?;
 
-                Ok(exp)
+                Ok(Expression::new(token.node.try_into().unwrap(), token.span))
             }
             LParen => {
                 self.take_unchecked()?;
-                let exp = self.nt_expression()?;
+                let mut exp = self.nt_expression()?;
                 self.take_checked(RParen)?;
 
+                // the parentheses are part of the expression
+                exp.span = self.span_from(start);
                 Ok(exp)
             }
             AddOp(Sub) => {
                 self.take_unchecked()?;
                 let exp = self.nt_primary()?;
 
-                Ok(Expression::Minus(Box::new(exp)))
+                Ok(Expression::new(
+                    ExpressionKind::Minus(Box::new(exp)),
+                    self.span_from(start),
+                ))
             }
             Not => {
                 self.take_unchecked()?;
+                let exp = self.nt_primary()?;
 
-                Ok(Expression::Not(Box::new(self.nt_primary()?)))
+                Ok(Expression::new(
+                    ExpressionKind::Not(Box::new(exp)),
+                    self.span_from(start),
+                ))
+            }
+            BitNot => {
+                self.take_unchecked()?;
+                let exp = self.nt_primary()?;
+
+                Ok(Expression::new(
+                    ExpressionKind::BitNot(Box::new(exp)),
+                    self.span_from(start),
+                ))
             }
             _ => Err(self.expected(&[
                 AddOp(Sub),
                 LParen,
-                Number(String::new()),
-                CharLiteral(None),
+                Number(0),
+                CharLiteral('\0'),
                 Identifier(String::new()),
                 StringLiteral(String::new()),
                 Not,
+                BitNot,
             ])),
         }?;
 
@@ -950,39 +1268,38 @@ impl Parser {
         Ok(res)
     }
 
-    /// `FunctionCall | ε`
-    fn nt_primary_(&mut self, id: String) -> Result<Expression> {
+    /// `FunctionCall | <[> Expression <]> | ε`
+    fn nt_primary_(&mut self, id: String, start: Span) -> Result<Expression> {
         self.debug("entering Primary'");
 
-        let res = match self.buffer {
+        let res = match self.buffer.node {
             LParen => {
                 let args = self.nt_function_call()?;
 
-                Ok(Expression::FuncCall(id, args))
+                Ok(Expression::new(
+                    ExpressionKind::FuncCall(id, args),
+                    self.span_from(start),
+                ))
+            }
+            LBracket => {
+                self.take_unchecked()?;
+                let index = self.nt_expression()?;
+                self.take_checked(RBracket)?;
+
+                Ok(Expression::new(
+                    ExpressionKind::Index(id, Box::new(index)),
+                    self.span_from(start),
+                ))
             }
-            Comma | Semicolon | AddOp(_) | RParen | AssignOp | MulOp(_) | RelOp(_) => {
-                Ok(Expression::Identifier(id))
+            ref token if Self::ends_operand(token) => Ok(Expression::new(
+                ExpressionKind::Identifier(id),
+                self.span_from(start),
+            )),
+            _ => {
+                let mut expected = vec![LParen, LBracket];
+                expected.extend(Self::operand_follow());
+                Err(self.expected(&expected))
             }
-            _ => Err(self.expected(&[
-                MulOp(BoolAnd),
-                MulOp(Div),
-                MulOp(Mod),
-                MulOp(Mul),
-                AddOp(Sub),
-                AddOp(Add),
-                AddOp(BoolOr),
-                Comma,
-                Semicolon,
-                LParen,
-                RParen,
-                RelOp(Gt),
-                RelOp(GtEq),
-                RelOp(Lt),
-                RelOp(LtEq),
-                RelOp(Eq),
-                RelOp(Neq),
-                AssignOp,
-            ])),
         }?;
 
         self.debug("exiting Primary'");
@@ -993,7 +1310,7 @@ impl Parser {
     fn nt_function_call(&mut self) -> Result<Vec<Expression>> {
         self.debug("entering FunctionCall");
 
-        let res = match self.buffer {
+        let res = match self.buffer.node {
             LParen => {
                 self.take_unchecked()?;
                 let expressions = self.nt_function_call_()?;
@@ -1012,20 +1329,20 @@ impl Parser {
     fn nt_function_call_(&mut self) -> Result<Vec<Expression>> {
         self.debug("entering FunctionCall'");
 
-        let res = match self.buffer {
-            StringLiteral(_) | Identifier(_) | CharLiteral(_) | AddOp(_) | Number(_) | Not
-            | LParen => self.nt_actual_parameters(),
+        let res = match self.buffer.node {
+            StringLiteral(_) | Identifier(_) | CharLiteral(_) | AddOp(Sub) | Number(_) | Not
+            | BitNot | LParen => self.nt_actual_parameters(),
             RParen => Ok(vec![]),
             _ => Err(self.expected(&[
                 AddOp(Sub),
-                AddOp(Add),
-                AddOp(BoolOr),
                 LParen,
                 RParen,
                 StringLiteral(String::new()),
                 Identifier(String::new()),
-                CharLiteral(None),
+                CharLiteral('\0'),
+                Number(0),
                 Not,
+                BitNot,
             ])),
         }?;
 
@@ -1037,9 +1354,9 @@ impl Parser {
     fn nt_actual_parameters(&mut self) -> Result<Vec<Expression>> {
         self.debug("entering ActualParameters");
 
-        let res = match self.buffer {
-            LParen | Not | CharLiteral(_) | StringLiteral(_) | Identifier(_) | Number(_)
-            | AddOp(Sub) => {
+        let res = match self.buffer.node {
+            LParen | Not | BitNot | CharLiteral(_) | StringLiteral(_) | Identifier(_)
+            | Number(_) | AddOp(Sub) => {
                 let expression = self.nt_expression()?;
                 let mut expressions = vec![expression];
                 self.nt_actual_parameters_(&mut expressions)?;
@@ -1049,11 +1366,11 @@ impl Parser {
             _ => Err(self.expected(&[
                 LParen,
                 Not,
-                LParen,
-                CharLiteral(None),
+                BitNot,
+                CharLiteral('\0'),
                 StringLiteral(String::new()),
                 Identifier(String::new()),
-                Number(String::new()),
+                Number(0),
                 AddOp(Sub),
             ])),
         }?;
@@ -1066,7 +1383,7 @@ impl Parser {
     fn nt_actual_parameters_(&mut self, expressions: &mut Vec<Expression>) -> Result<()> {
         self.debug("entering ActualParameters'");
 
-        match self.buffer {
+        match self.buffer.node {
             Comma => {
                 self.take_unchecked()?;
                 let expression = self.nt_expression()?;
@@ -1074,7 +1391,7 @@ impl Parser {
                 self.nt_actual_parameters_(expressions)
             }
             RParen => Ok(()),
-            _ => Err(self.expected(&[LParen, Comma])),
+            _ => Err(self.expected(&[Comma, RParen])),
         }?;
 
         self.debug("exiting ActualParameters'");
@@ -0,0 +1,163 @@
+//! EGRE 591 part3 - Nathan Rowan and Trevin Vaughan
+//!
+//! The precedence table for infix operators, which are parsed by precedence climbing.
+//! The levels are the same as in C.
+
+use crate::scanner::token::{
+    AddOp::*,
+    BitOp::*,
+    MulOp::*,
+    RelOp::*,
+    Token::{self, *},
+};
+
+use super::ast::Operator;
+
+/// How tightly an operator binds, from the loosest to the tightest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precedence {
+    /// `=`
+    Assignment,
+    /// `?:`
+    Conditional,
+    /// `||`
+    LogicalOr,
+    /// `&&`
+    LogicalAnd,
+    /// `|`
+    BitwiseOr,
+    /// `^`
+    BitwiseXor,
+    /// `&`
+    BitwiseAnd,
+    /// `==` and `!=`
+    Equality,
+    /// `<`, `<=`, `>` and `>=`
+    Relational,
+    /// `<<` and `>>`
+    Shift,
+    /// `+` and `-`
+    Additive,
+    /// `*`, `/` and `%`
+    Multiplicative,
+    /// unary `-`, `!` and `~`, which are parsed along with their operand
+    Unary,
+}
+
+impl Precedence {
+    /// The level right above this one
+    pub fn tighter(self) -> Self {
+        match self {
+            Precedence::Assignment => Precedence::Conditional,
+            Precedence::Conditional => Precedence::LogicalOr,
+            Precedence::LogicalOr => Precedence::LogicalAnd,
+            Precedence::LogicalAnd => Precedence::BitwiseOr,
+            Precedence::BitwiseOr => Precedence::BitwiseXor,
+            Precedence::BitwiseXor => Precedence::BitwiseAnd,
+            Precedence::BitwiseAnd => Precedence::Equality,
+            Precedence::Equality => Precedence::Relational,
+            Precedence::Relational => Precedence::Shift,
+            Precedence::Shift => Precedence::Additive,
+            Precedence::Additive => Precedence::Multiplicative,
+            Precedence::Multiplicative | Precedence::Unary => Precedence::Unary,
+        }
+    }
+}
+
+/// Which way a chain of operators at the same level is grouped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    /// `a - b - c` is `(a - b) - c`
+    Left,
+    /// `a = b = c` is `a = (b = c)`
+    Right,
+}
+
+/// What an infix operator builds out of the expressions around it
+#[derive(Debug, Clone, Copy)]
+pub enum InfixKind {
+    /// A binary operation, including assignment
+    Binary(Operator),
+    /// A conditional expression, the only ternary operator, whose middle expression is
+    /// between the `?` and the `:`
+    Ternary,
+}
+
+/// A row of the precedence table
+#[derive(Debug)]
+pub struct Infix {
+    /// the token which starts the operator
+    pub token: Token,
+    /// what the operator builds
+    pub kind: InfixKind,
+    /// how tightly the operator binds
+    pub precedence: Precedence,
+    /// how a chain of operators at the same level is grouped
+    pub associativity: Associativity,
+}
+
+impl Infix {
+    /// Precedence of the right hand side, which only takes tighter operators unless this is
+    /// right associative
+    pub fn rhs_precedence(&self) -> Precedence {
+        match self.associativity {
+            Associativity::Left => self.precedence.tighter(),
+            Associativity::Right => self.precedence,
+        }
+    }
+}
+
+/// Short-hand for a row of the table
+const fn row(
+    token: Token,
+    kind: InfixKind,
+    precedence: Precedence,
+    associativity: Associativity,
+) -> Infix {
+    Infix {
+        token,
+        kind,
+        precedence,
+        associativity,
+    }
+}
+
+/// Every infix operator, from the loosest to the tightest
+const TABLE: &[Infix] = {
+    use Associativity::*;
+    use InfixKind::*;
+    use Precedence::*;
+
+    &[
+        row(AssignOp, Binary(Operator::Assign), Assignment, Right),
+        row(Question, Ternary, Conditional, Right),
+        row(AddOp(BoolOr), Binary(Operator::BoolOr), LogicalOr, Left),
+        row(MulOp(BoolAnd), Binary(Operator::BoolAnd), LogicalAnd, Left),
+        row(BitOp(Or), Binary(Operator::BitOr), BitwiseOr, Left),
+        row(BitOp(Xor), Binary(Operator::BitXor), BitwiseXor, Left),
+        row(BitOp(And), Binary(Operator::BitAnd), BitwiseAnd, Left),
+        row(RelOp(Eq), Binary(Operator::Eq), Equality, Left),
+        row(RelOp(Neq), Binary(Operator::Neq), Equality, Left),
+        row(RelOp(Lt), Binary(Operator::Lt), Relational, Left),
+        row(RelOp(LtEq), Binary(Operator::LtEq), Relational, Left),
+        row(RelOp(Gt), Binary(Operator::Gt), Relational, Left),
+        row(RelOp(GtEq), Binary(Operator::GtEq), Relational, Left),
+        row(BitOp(Shl), Binary(Operator::Shl), Shift, Left),
+        row(BitOp(Shr), Binary(Operator::Shr), Shift, Left),
+        row(AddOp(Add), Binary(Operator::Add), Additive, Left),
+        row(AddOp(Sub), Binary(Operator::Sub), Additive, Left),
+        row(MulOp(Mul), Binary(Operator::Mul), Multiplicative, Left),
+        row(MulOp(Div), Binary(Operator::Div), Multiplicative, Left),
+        row(MulOp(Mod), Binary(Operator::Mod), Multiplicative, Left),
+    ]
+};
+
+/// Finds the infix operator started by a token
+pub fn infix(token: &Token) -> Option<&'static Infix> {
+    TABLE.iter().find(|infix| infix.token == *token)
+}
+
+/// Tokens of every infix operator, for syntax errors
+pub fn operators() -> impl Iterator<Item = Token> {
+    TABLE.iter().map(|infix| infix.token.clone())
+}
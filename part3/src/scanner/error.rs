@@ -4,6 +4,8 @@
 
 use std::{fmt::Display, io};
 
+use crate::diagnostic::Coded;
+
 /// Types of errors that can happen during scanning.
 #[derive(Debug)]
 pub enum Error {
@@ -17,6 +19,18 @@ pub enum Error {
     NewlineInStringLiteral,
     /// EOF reached before closing " character
     UnclosedStringLiteral,
+    /// A backslash in a literal followed by a character that isn't an escape
+    InvalidEscape(char),
+    /// '\x' not followed by two hexadecimal digits
+    InvalidHexEscape,
+    /// A number literal that doesn't fit in an int
+    NumberTooLarge(String),
+    /// A number literal with digits that aren't allowed in its base, or letters after it
+    InvalidNumber(String),
+    /// A floating-point number literal, which ToyC has no type for
+    FloatLiteral(String),
+    /// A char literal without a character
+    EmptyCharLiteral,
     /// Errors occurring because of I/O
     Io(io::Error),
 }
@@ -35,6 +49,14 @@ impl Display for Error {
             Error::UnclosedCharLiteral => "unclosed character literal",
             Error::NewlineInStringLiteral => "newline in string literal",
             Error::UnclosedStringLiteral => "unclosed string literal",
+            Error::InvalidEscape(c) => return write!(f, "unknown escape sequence \\{}", c),
+            Error::InvalidHexEscape => "\\x must be followed by two hexadecimal digits",
+            Error::NumberTooLarge(n) => return write!(f, "number {} does not fit in an int", n),
+            Error::InvalidNumber(n) => return write!(f, "{} is not a valid number", n),
+            Error::FloatLiteral(n) => {
+                return write!(f, "floating-point number {} is not supported", n)
+            }
+            Error::EmptyCharLiteral => "empty character literal",
             Error::Io(e) => return write!(f, "i/o error occurred ({:?})", e),
         };
 
@@ -42,6 +64,25 @@ impl Display for Error {
     }
 }
 
+impl Coded for Error {
+    fn code(&self) -> &'static str {
+        match self {
+            Error::UnclosedComment => "T0101",
+            Error::NewlineInCharLiteral => "T0102",
+            Error::UnclosedCharLiteral => "T0103",
+            Error::NewlineInStringLiteral => "T0104",
+            Error::UnclosedStringLiteral => "T0105",
+            Error::Io(_) => "T0106",
+            Error::InvalidEscape(_) => "T0108",
+            Error::InvalidHexEscape => "T0109",
+            Error::NumberTooLarge(_) => "T0110",
+            Error::InvalidNumber(_) => "T0111",
+            Error::FloatLiteral(_) => "T0112",
+            Error::EmptyCharLiteral => "T0113",
+        }
+    }
+}
+
 /// Warnings that can be generated by the scanner. These are simply printed and ignored.
 #[derive(Debug)]
 pub enum Warning {
@@ -60,3 +101,11 @@ impl Display for Warning {
         write!(f, "{}", str)
     }
 }
+
+impl Coded for Warning {
+    fn code(&self) -> &'static str {
+        match self {
+            Warning::IllegalCharacter => "T0107",
+        }
+    }
+}
@@ -3,6 +3,9 @@
 //! Implementation note: originally we had used a boolean to represent
 //! that a character must be re-scanned. It became obvious that this is
 //! equivalent to `token.is_some()`, so the logic has been simplified.
+//!
+//! Errors are recovered from: the FSM moves to a state that finishes the broken
+//! token, and the character is re-scanned, just like when a token is returned.
 
 use std::str::FromStr;
 
@@ -16,12 +19,14 @@ use crate::scanner::token::*;
 /// (or warning) that occurred.
 #[derive(Default, Clone)]
 pub struct Fsm {
-    /// Current state, represented as an 8-bit unsigned integer (max value: 34)
+    /// Current state, represented as an 8-bit unsigned integer (max value: 47)
     state: u8,
     /// Current token being scanned, used to fill attribute fields
     token: String,
     /// Current number of nested comment tags
     comment_level: usize,
+    /// Value of the first digit of a `\xNN` escape sequence
+    hex: u32,
 }
 
 impl Fsm {
@@ -58,6 +63,60 @@ impl Fsm {
         }
     }
 
+    /// Converts the number being scanned into an int.
+    ///
+    /// Decimal numbers must fit in an int, but numbers with a base prefix (`0x`, `0o`,
+    /// `0b` or a leading `0` for octal) may use all 32 bits, like in Java.
+    fn make_number(&self) -> Result<Token, Error> {
+        let text = self.token.as_str();
+
+        let prefixed = |prefix: [&str; 2]| {
+            text.strip_prefix(prefix[0])
+                .or_else(|| text.strip_prefix(prefix[1]))
+        };
+        let (digits, radix) = if let Some(digits) = prefixed(["0x", "0X"]) {
+            (digits, 16)
+        } else if let Some(digits) = prefixed(["0o", "0O"]) {
+            (digits, 8)
+        } else if let Some(digits) = prefixed(["0b", "0B"]) {
+            (digits, 2)
+        } else if self.state == 42 {
+            // letters ran into a number without a base prefix
+            return Err(Error::InvalidNumber(text.to_owned()));
+        } else if text.contains(['.', 'E']) {
+            return Err(Error::FloatLiteral(text.to_owned()));
+        } else if text.len() > 1 && text.starts_with('0') {
+            (&text[1..], 8)
+        } else {
+            return text
+                .parse()
+                .map(Token::Number)
+                .map_err(|_| Error::NumberTooLarge(text.to_owned()));
+        };
+
+        // from_str_radix would accept a sign, which is never part of a number token
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+            return Err(Error::InvalidNumber(text.to_owned()));
+        }
+
+        u32::from_str_radix(digits, radix)
+            .map(|n| Token::Number(n as i32))
+            .map_err(|_| Error::NumberTooLarge(text.to_owned()))
+    }
+
+    /// Returns the number token being scanned, recovering if it isn't a valid int
+    fn return_number(&mut self) -> Result<(Option<Token>, Option<Warning>), Error> {
+        match self.make_number() {
+            Ok(t) => self.return_token(t),
+            Err(e) => self.recover(43, e),
+        }
+    }
+
+    /// Returns the char literal being scanned, after its error has been reported
+    fn make_char(&self) -> Token {
+        Token::CharLiteral(self.token.chars().next().unwrap_or('\0'))
+    }
+
     /// Returns relop after successful matching of current state
     fn make_relop(&self) -> Token {
         match (self.state, self.token.as_str()) {
@@ -92,16 +151,54 @@ impl Fsm {
         }
     }
 
+    /// Returns bitop after successful matching of current state
+    fn make_bitop(&self) -> Token {
+        match self.token.as_str() {
+            "&" => Token::BitOp(BitOp::And),
+            "|" => Token::BitOp(BitOp::Or),
+            "^" => Token::BitOp(BitOp::Xor),
+            "<" => Token::BitOp(BitOp::Shl),
+            ">" => Token::BitOp(BitOp::Shr),
+            _ => unreachable!("make_bitop() called with unknown state"),
+        }
+    }
+
+    /// Moves to a state that finishes a broken token, then returns the error.
+    ///
+    /// The caller of this function must ensure that the input character is re-scanned.
+    fn recover(&mut self, edge: u8, e: Error) -> Result<(Option<Token>, Option<Warning>), Error> {
+        self.state = edge;
+        Err(e)
+    }
+
+    /// Decodes the character after a backslash, for escapes that are a single character
+    fn unescape(c: char) -> Option<char> {
+        match c {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            '\\' => Some('\\'),
+            '\'' => Some('\''),
+            '"' => Some('"'),
+            '0' => Some('\0'),
+            _ => None,
+        }
+    }
+
     /// Returns an illegal character warning and no token
     fn warn_illegal_character(&self) -> Result<(Option<Token>, Option<Warning>), Error> {
         Ok((None, Some(Warning::IllegalCharacter)))
     }
 
+    /// Whether the FSM is between tokens, so the next character scanned may start a new one
+    pub fn is_idle(&self) -> bool {
+        self.state == 0
+    }
+
     /// Implementation of the DFA transitions.
     ///
     /// Can return an error, or a pair of an optional token and optional warning.
     ///
-    /// The current character must be re-scanned iff a token is returned.
+    /// The current character must be re-scanned iff a token or an error is returned.
     ///
     /// Note: accepting states only return a value on *the next edge*.
     pub fn step(&mut self, c: char) -> Result<(Option<Token>, Option<Warning>), Error> {
@@ -124,9 +221,11 @@ impl Fsm {
                         '!' => self.take_edge_and_push(19, c), // inequality
                         '<' | '>' => self.take_edge_and_push(20, c), // relop
                         '+' | '-' => self.take_edge_and_push(21, c), // addop
-                        '|' => self.take_edge_and_push(22, c), // bool or
+                        '|' => self.take_edge_and_push(22, c), // bool or, bit or
                         '*' | '%' => self.take_edge_and_push(23, c), // mulop
-                        '&' => self.take_edge_and_push(24, c), // bool and
+                        '&' => self.take_edge_and_push(24, c), // bool and, bit and
+                        '^' => self.take_edge_and_push(46, c), // bit xor
+                        '~' => self.take_edge(47),             // bit not
                         '(' => self.take_edge(25),             // lparen
                         ')' => self.take_edge(26),             // rparen
                         '{' => self.take_edge(27),             // lcurly
@@ -136,6 +235,7 @@ impl Fsm {
                         ',' => self.take_edge(31),             // comma
                         ';' => self.take_edge(32),             // semicolon
                         ':' => self.take_edge(33),             // colon
+                        '?' => self.take_edge(45),             // question mark
                         _ => self.warn_illegal_character(),
                     }
                 }
@@ -176,7 +276,11 @@ impl Fsm {
                 '0'..='9' => self.take_edge_and_push(6, c),
                 '.' => self.take_edge_and_push(7, c),
                 'E' => self.take_edge_and_push(9, c),
-                _ => self.return_token(Token::Number(self.token.clone())),
+                'x' | 'X' | 'o' | 'O' | 'b' | 'B' if self.token == "0" => {
+                    self.take_edge_and_push(42, c)
+                }
+                'A'..='Z' | 'a'..='z' => self.take_edge_and_push(42, c),
+                _ => self.return_number(),
             },
             7 => match c {
                 '0'..='9' => self.take_edge_and_push(8, c),
@@ -185,7 +289,8 @@ impl Fsm {
             8 => match c {
                 '0'..='9' => self.take_edge_and_push(8, c),
                 'E' => self.take_edge_and_push(9, c),
-                _ => self.return_token(Token::Number(self.token.clone())),
+                'A'..='Z' | 'a'..='z' => self.take_edge_and_push(42, c),
+                _ => self.return_number(),
             },
             9 => match c {
                 '0'..='9' => self.take_edge_and_push(11, c),
@@ -198,21 +303,27 @@ impl Fsm {
             },
             11 => match c {
                 '0'..='9' => self.take_edge_and_push(11, c),
-                _ => self.return_token(Token::Number(self.token.clone())),
+                'A'..='Z' | 'a'..='z' => self.take_edge_and_push(42, c),
+                _ => self.return_number(),
             },
             12 => match c {
                 '\'' => self.take_edge(13),
-                '\n' => Err(Error::NewlineInCharLiteral),
+                '\\' => self.take_edge(36),
+                '\n' => self.recover(44, Error::NewlineInCharLiteral),
                 _ => self.take_edge_and_push(14, c),
             },
-            13 => self.return_token(Token::CharLiteral(self.token.chars().nth(0))),
+            13 => match self.token.chars().next() {
+                Some(c) => self.return_token(Token::CharLiteral(c)),
+                None => self.recover(44, Error::EmptyCharLiteral),
+            },
             14 => match c {
                 '\'' => self.take_edge(13),
-                _ => Err(Error::UnclosedCharLiteral),
+                _ => self.recover(35, Error::UnclosedCharLiteral), // skip to the closing '
             },
             15 => match c {
                 '"' => self.take_edge(16),
-                '\n' => Err(Error::NewlineInStringLiteral),
+                '\\' => self.take_edge(37),
+                '\n' => self.recover(16, Error::NewlineInStringLiteral), // end the string early
                 _ => self.take_edge_and_push(15, c),
             },
             16 => self.return_token(Token::StringLiteral(self.token.clone())),
@@ -227,17 +338,18 @@ impl Fsm {
             },
             20 => match c {
                 '=' => self.take_edge(18),
+                '<' | '>' if self.token.starts_with(c) => self.take_edge(46),
                 _ => self.return_token(self.make_relop()),
             },
             21 => self.return_token(self.make_addop()),
             22 => match c {
                 '|' => self.take_edge(21),
-                _ => self.warn_illegal_character(),
+                _ => self.return_token(self.make_bitop()),
             },
             23 => self.return_token(self.make_mulop()),
             24 => match c {
                 '&' => self.take_edge(23),
-                _ => self.warn_illegal_character(),
+                _ => self.return_token(self.make_bitop()),
             },
             25 => self.return_token(Token::LParen),
             26 => self.return_token(Token::RParen),
@@ -255,6 +367,61 @@ impl Fsm {
                 }
                 _ => self.take_edge(3),
             },
+            35 => match c {
+                '\'' => self.take_edge(13),
+                '\n' => self.return_token(self.make_char()),
+                _ => self.take_edge(35),
+            },
+            // escape sequences in char literals (36, 38, 40) and string literals (37, 39, 41)
+            36 => match c {
+                '\n' => self.recover(44, Error::NewlineInCharLiteral),
+                'x' => self.take_edge(38),
+                _ => match Self::unescape(c) {
+                    Some(e) => self.take_edge_and_push(14, e),
+                    None => self.recover(12, Error::InvalidEscape(c)), // keep the character as-is
+                },
+            },
+            37 => match c {
+                '\n' => self.recover(16, Error::NewlineInStringLiteral),
+                'x' => self.take_edge(39),
+                _ => match Self::unescape(c) {
+                    Some(e) => self.take_edge_and_push(15, e),
+                    None => self.recover(15, Error::InvalidEscape(c)), // keep the character as-is
+                },
+            },
+            38 | 39 => match c.to_digit(16) {
+                Some(d) => {
+                    self.hex = d;
+                    self.take_edge(self.state + 2)
+                }
+                // the escape is dropped
+                None if self.state == 38 => self.recover(12, Error::InvalidHexEscape),
+                None => self.recover(15, Error::InvalidHexEscape),
+            },
+            40 | 41 => {
+                // the literal goes on after the escape either way
+                let next = if self.state == 40 { 14 } else { 15 };
+                match c.to_digit(16) {
+                    Some(d) => self.take_edge_and_push(next, char::from((self.hex * 16 + d) as u8)),
+                    None => {
+                        self.token.push(char::from(self.hex as u8));
+                        self.recover(next, Error::InvalidHexEscape)
+                    }
+                }
+            }
+            // digits after a base prefix, or letters running into a number, which are all
+            // part of the number and checked against its base afterwards
+            42 => match c {
+                '0'..='9' | 'A'..='Z' | 'a'..='z' => self.take_edge_and_push(42, c),
+                _ => self.return_number(),
+            },
+            // an invalid number is replaced with 0 so that parsing can go on
+            43 => self.return_token(Token::Number(0)),
+            // likewise, a broken char literal is replaced with a null character
+            44 => self.return_token(Token::CharLiteral('\0')),
+            45 => self.return_token(Token::Question),
+            46 => self.return_token(self.make_bitop()),
+            47 => self.return_token(Token::BitNot),
             _ => unreachable!("step() called with unknown state"),
         }
     }
@@ -287,16 +454,25 @@ impl Fsm {
             2 => self.finish_none(), // comment at the end of the file
             3 | 4 | 34 => self.finish_err(Error::UnclosedComment),
             5 => Self::finish_token(self.make_id_or_keyword()),
-            6 | 8 | 11 => Self::finish_token(Token::Number(self.token)),
-            12 | 14 => self.finish_err(Error::UnclosedCharLiteral),
-            13 => Self::finish_token(Token::CharLiteral(self.token.chars().nth(0))),
-            15 => self.finish_err(Error::UnclosedStringLiteral),
+            6 | 8 | 11 | 42 => Self::finish_token(self.make_number()?),
+            43 => Self::finish_token(Token::Number(0)),
+            12 | 14 | 36 | 38 | 40 => self.finish_err(Error::UnclosedCharLiteral),
+            13 => match self.token.chars().next() {
+                Some(c) => Self::finish_token(Token::CharLiteral(c)),
+                None => self.finish_err(Error::EmptyCharLiteral),
+            },
+            35 => Self::finish_token(self.make_char()),
+            44 => Self::finish_token(Token::CharLiteral('\0')),
+            45 => Self::finish_token(Token::Question),
+            15 | 37 | 39 | 41 => self.finish_err(Error::UnclosedStringLiteral),
             16 => Self::finish_token(Token::StringLiteral(self.token)),
             17 => Self::finish_token(Token::AssignOp),
             18 | 20 => Self::finish_token(self.make_relop()),
             19 => Self::finish_token(Token::Not),
             21 => Self::finish_token(self.make_addop()),
             23 => Self::finish_token(self.make_mulop()),
+            22 | 24 | 46 => Self::finish_token(self.make_bitop()),
+            47 => Self::finish_token(Token::BitNot),
             25 => Self::finish_token(Token::LParen),
             26 => Self::finish_token(Token::RParen),
             27 => Self::finish_token(Token::LCurly),
@@ -306,7 +482,7 @@ impl Fsm {
             31 => Self::finish_token(Token::Comma),
             32 => Self::finish_token(Token::Semicolon),
             33 => Self::finish_token(Token::Colon),
-            35.. => unreachable!("finish() called with unknown state"),
+            48.. => unreachable!("finish() called with unknown state"),
             _ => self.finish_illegal_char(),
         }
     }
@@ -4,9 +4,11 @@
 
 use std::{fmt::Display, path::Path};
 
-use colored::Colorize;
-
-use crate::{context::Context, file_buffer::FileBuffer};
+use crate::{
+    context::Context,
+    file_buffer::FileBuffer,
+    span::{Position, Span, Spanned},
+};
 
 pub mod error;
 mod fsm;
@@ -36,6 +38,12 @@ pub struct Scanner {
     eof: bool,
     /// Internal count of the number of tokens returned
     token_count: usize,
+    /// Position of the first character of the token being scanned
+    start: Position,
+    /// Errors that have been recovered from, in the order they were found
+    errors: Vec<Context<Error>>,
+    /// Warnings, in the order they were found
+    warnings: Vec<Context<Warning>>,
     /// File buffer
     file_buffer: FileBuffer,
 }
@@ -56,6 +64,9 @@ impl Scanner {
             file_buffer,
             eof: false,
             token_count: 0,
+            start: Position::default(),
+            errors: Vec::new(),
+            warnings: Vec::new(),
         })
     }
 
@@ -66,7 +77,7 @@ impl Scanner {
 
     /// Attempts to make an EOF token, returning [Some(Ok(Token::Eof))] on the first
     /// call and [None] on subsequent calls.
-    fn make_eof_token(&mut self) -> Option<Token> {
+    fn make_eof_token(&mut self) -> Option<Spanned<Token>> {
         if !self.eof {
             self.eof = true;
             self.token_count += 1;
@@ -74,23 +85,50 @@ impl Scanner {
                 println!("[SCANNER] {}", Token::Eof);
                 println!("[SCANNER] Total tokens: {}", self.token_count);
             }
-            Some(Token::Eof)
+
+            // EOF takes up no space at the end of the file
+            let position = self.file_buffer.position();
+            Some(Spanned::new(Token::Eof, Span::new(position, position)))
         } else {
             None
         }
     }
 
+    /// Gives a token the span from its first character up to the current character
+    fn make_token(&mut self, t: Token) -> Spanned<Token> {
+        self.token_count += 1;
+        self.debug_print_token(&t);
+        Spanned::new(t, Span::new(self.start, self.file_buffer.position()))
+    }
+
     /// Add context to a given error
     #[allow(clippy::missing_panics_doc)] // constructor guarantees this won't panic
     pub fn context<T: Display>(&self, t: T) -> Context<T> {
         self.file_buffer.context(t).unwrap()
     }
 
-    /// Prints warnings with context
+    /// Add context to an error found by the FSM
     ///
-    /// This is not a method function because in the context of the loop, the borrow check fails.
-    fn print_warning(f: &FileBuffer, w: Warning) {
-        eprintln!("{} {}", "[WARNING]".yellow(), f.context(w).unwrap());
+    /// This doesn't borrow the whole scanner, since the FSM is borrowed while it runs.
+    fn error_context(file_buffer: &FileBuffer, start: Position, e: Error) -> Context<Error> {
+        match e {
+            // these are found once the whole number is scanned, so they cover all of it
+            Error::NumberTooLarge(_) | Error::InvalidNumber(_) | Error::FloatLiteral(_) => {
+                file_buffer.context_from(e, start)
+            }
+            _ => file_buffer.context(e),
+        }
+        .unwrap()
+    }
+
+    /// Takes the errors found since the last call, leaving none behind
+    pub fn take_errors(&mut self) -> Vec<Context<Error>> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Takes the warnings found since the last call, leaving none behind
+    pub fn take_warnings(&mut self) -> Vec<Context<Warning>> {
+        std::mem::take(&mut self.warnings)
     }
 
     /// Prints tokens in debug mode
@@ -102,12 +140,15 @@ impl Scanner {
 }
 
 impl Iterator for Scanner {
-    type Item = Result<Token, Context<Error>>;
+    type Item = Result<Spanned<Token>, Context<Error>>;
 
     /// Implementation of iterator. Points worth noting in this API:
     /// - `Some(Ok(T))` indicates that the scanning happened with no errors
-    /// - `Some(Error(T))` indicates that the scanner returned an error, and the
-    ///    caller may either ignore this error or abort scanning. (Warnings are printed)
+    /// - `Some(Error(T))` indicates that the scanner returned an I/O error, and the
+    ///   caller may either ignore this error or abort scanning.
+    ///
+    /// Errors in the input itself are recovered from and collected along with warnings,
+    /// see [Self::take_errors] and [Self::take_warnings].
     /// - [None] indicates that the scanner has completed scanning the file and the
     ///   iterator may be discarded. It is crucial that this is not returned early.
     ///
@@ -125,16 +166,19 @@ impl Iterator for Scanner {
                 println!("[SCANNER] Running state machine against char {}", c);
             }
 
+            // tokens start on the character that takes the FSM out of its idle state
+            if fsm.is_idle() {
+                self.start = self.file_buffer.position();
+            }
+
             // 3: Attempt to run state machine
             match fsm.step(c) {
                 Ok((t, w)) => {
                     if let Some(w) = w {
-                        Self::print_warning(&self.file_buffer, w);
+                        self.warnings.push(self.file_buffer.context(w).unwrap());
                     }
                     if let Some(t) = t {
-                        self.token_count += 1;
-                        self.debug_print_token(&t);
-                        return Some(Ok(t));
+                        return Some(Ok(self.make_token(t)));
                     }
 
                     // if no token was returned, advance the buffer
@@ -146,7 +190,11 @@ impl Iterator for Scanner {
                         return Some(Err(e.map_kind(Error::Io)));
                     }
                 }
-                Err(e) => return Some(Err(self.context(e))),
+                // the FSM has recovered, so the character is re-scanned
+                Err(e) => {
+                    let context = Self::error_context(&self.file_buffer, self.start, e);
+                    self.errors.push(context)
+                }
             }
         }
 
@@ -154,17 +202,20 @@ impl Iterator for Scanner {
         match self.finish_fsm() {
             Ok((t, w)) => {
                 if let Some(w) = w {
-                    Self::print_warning(&self.file_buffer, w);
+                    self.warnings.push(self.context(w));
                 }
                 if let Some(t) = t {
-                    self.token_count += 1;
-                    self.debug_print_token(&t);
-                    Some(Ok(t))
+                    Some(Ok(self.make_token(t)))
                 } else {
                     self.make_eof_token().map(Ok)
                 }
             }
-            Err(e) => Some(Err(self.context(e))),
+            // nothing is left to scan, so the broken token is dropped
+            Err(e) => {
+                let context = Self::error_context(&self.file_buffer, self.start, e);
+                self.errors.push(context);
+                self.make_eof_token().map(Ok)
+            }
         }
     }
 }
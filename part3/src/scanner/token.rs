@@ -213,6 +213,57 @@ impl Display for MulOp {
     }
 }
 
+/// Bitwise and shift operators recognized by the scanner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOp {
+    /// &
+    And,
+    /// |
+    Or,
+    /// ^
+    Xor,
+    /// <<
+    Shl,
+    /// >>
+    Shr,
+}
+
+impl Display for BitOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            BitOp::And => "&",
+            BitOp::Or => "|",
+            BitOp::Xor => "^",
+            BitOp::Shl => "<<",
+            BitOp::Shr => ">>",
+        };
+
+        write!(f, "{}", str)
+    }
+}
+
+/// Writes text the way it would be written inside a literal with the given quotes, using
+/// escape sequences for anything that can't be written as-is
+pub fn escape(s: &str, quote: char) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\n' => escaped += "\\n",
+            '\t' => escaped += "\\t",
+            '\\' => escaped += "\\\\",
+            c if c == quote => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\0' => escaped += "\\0",
+            // every control character is below 0x100
+            c if c.is_control() => escaped += &format!("\\x{:02x}", c as u32),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 /// All token classes recognized by the scanner (and their annotations)
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Token {
@@ -220,11 +271,11 @@ pub enum Token {
     Keyword(Keyword),
     /// Identifiers (contains ASCII letters and digits)
     Identifier(String),
-    /// Numbers (Note: conversion to floating-point or integer types not yet implemented)
-    Number(String),
-    /// Character literals (empty allowed, built in unicode support)
-    CharLiteral(Option<char>),
-    /// String literals (empty allowed, built in unicode support)
+    /// Integer numbers, in any base
+    Number(i32),
+    /// Character literals (built in unicode support), with escapes decoded
+    CharLiteral(char),
+    /// String literals (empty allowed, built in unicode support), with escapes decoded
     StringLiteral(String),
     /// Relational operators (empty allowed)
     RelOp(RelOp),
@@ -232,6 +283,8 @@ pub enum Token {
     AddOp(AddOp),
     /// Multiplication operators
     MulOp(MulOp),
+    /// Bitwise and shift operators
+    BitOp(BitOp),
     /// =
     AssignOp,
     /// (
@@ -252,8 +305,12 @@ pub enum Token {
     Semicolon,
     /// !
     Not,
+    /// ~
+    BitNot,
     /// :
     Colon,
+    /// ?
+    Question,
     /// End of File
     Eof,
 }
@@ -272,6 +329,7 @@ impl Token {
             (Token::RelOp(l), Token::RelOp(r)) => l == r,
             (Token::AddOp(l), Token::AddOp(r)) => l == r,
             (Token::MulOp(l), Token::MulOp(r)) => l == r,
+            (Token::BitOp(l), Token::BitOp(r)) => l == r,
             (Token::AssignOp, Token::AssignOp) => true,
             (Token::LParen, Token::LParen) => true,
             (Token::RParen, Token::RParen) => true,
@@ -282,7 +340,9 @@ impl Token {
             (Token::Comma, Token::Comma) => true,
             (Token::Semicolon, Token::Semicolon) => true,
             (Token::Not, Token::Not) => true,
+            (Token::BitNot, Token::BitNot) => true,
             (Token::Colon, Token::Colon) => true,
+            (Token::Question, Token::Question) => true,
             (Token::Eof, Token::Eof) => true,
             (_, _) => false,
         }
@@ -317,6 +377,13 @@ impl Token {
                 MulOp::Mod => "'%'",
                 MulOp::Mul => "'*'",
             },
+            Self::BitOp(op) => match op {
+                BitOp::And => "'&'",
+                BitOp::Or => "'|'",
+                BitOp::Xor => "'^'",
+                BitOp::Shl => "'<<'",
+                BitOp::Shr => "'>>'",
+            },
             Self::AssignOp => "'='",
             Self::LParen => "'('",
             Self::RParen => "')'",
@@ -327,7 +394,9 @@ impl Token {
             Self::Comma => "','",
             Self::Semicolon => "';'",
             Self::Not => "'!'",
+            Self::BitNot => "'~'",
             Self::Colon => "':'",
+            Self::Question => "'?'",
             Self::Eof => "<EOF>",
         }
     }
@@ -338,15 +407,13 @@ impl Display for Token {
         let (class, attribute) = match self {
             Token::Keyword(k) => (k.to_upper(), k.to_string()),
             Token::Identifier(s) => ("ID", s.clone()),
-            Token::Number(s) => ("NUMBER", s.clone()),
-            Token::CharLiteral(c) => (
-                "CHARLITERAL",
-                c.map(|c| c.to_string()).unwrap_or(String::new()),
-            ),
-            Token::StringLiteral(s) => ("STRING", String::from("\"") + s + "\""),
+            Token::Number(n) => ("NUMBER", n.to_string()),
+            Token::CharLiteral(c) => ("CHARLITERAL", escape(&c.to_string(), '\'')),
+            Token::StringLiteral(s) => ("STRING", String::from("\"") + &escape(s, '"') + "\""),
             Token::RelOp(k) => ("RELOP", k.to_string()),
             Token::AddOp(k) => ("ADDOP", k.to_string()),
             Token::MulOp(k) => ("MULOP", k.to_string()),
+            Token::BitOp(k) => ("BITOP", k.to_string()),
             Token::AssignOp => ("ASSIGNOP", "=".to_string()),
             Token::LParen => ("LPAREN", "(".to_string()),
             Token::RParen => ("RPAREN", ")".to_string()),
@@ -357,7 +424,9 @@ impl Display for Token {
             Token::Comma => ("COMMA", ",".to_owned()),
             Token::Semicolon => ("SEMICOLON", ";".to_owned()),
             Token::Not => ("NOT", "!".to_owned()),
+            Token::BitNot => ("BITNOT", "~".to_owned()),
             Token::Colon => ("COLON", ":".to_owned()),
+            Token::Question => ("QUESTION", "?".to_owned()),
             Token::Eof => ("EOF", "EOF".to_owned()),
         };
         write!(f, "token {} {}", class, attribute)
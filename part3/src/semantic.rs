@@ -0,0 +1,841 @@
+//! EGRE 591 part3 - Nathan Rowan and Trevin Vaughan
+//!
+//! Semantic analysis, which sits between the parser and the back-ends. Identifiers are resolved,
+//! types are checked, and the result is a [typed::Program] that back-ends can trust.
+
+mod printing;
+pub mod typed;
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use crate::{
+    context::MaybeContext,
+    diagnostic::Coded,
+    parser::ast,
+    source::Source,
+    span::{Span, Spanned},
+};
+use typed::{
+    Expression, ExpressionKind, Function, Program, Statement, Storage, SwitchCase, Type, Variable,
+};
+
+/// Errors found during semantic analysis
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// A function was declared in a non-global scope
+    NonGlobalFunction(String),
+    /// A name was re-used in an illegal way (shadowing is allowed)
+    NameCollision(String),
+    /// Main function was not found
+    MissingMain,
+    /// Return statement without a value
+    InvalidReturn,
+    /// Function invocation uses different parameters than expected
+    InvalidSubroutineParameters,
+    /// Division by zero is undefined
+    DivisionByZero,
+    /// A function was missing
+    MissingFunction(String),
+    /// A break statement was used outside of a loop or switch
+    BreakOutsideLoop,
+    /// A continue statement was used outside of a loop
+    ContinueOutsideLoop,
+    /// A variable was missing
+    MissingVariable(String),
+    /// Assigns to something other than an identifier
+    InvalidAssign,
+    /// Incompatible expression types
+    IncompatibleTypes,
+    /// Something other than an array was indexed
+    NotAnArray(String),
+    /// A switch has two cases with the same value, or two default cases
+    DuplicateCase(Option<i32>),
+    /// An array was declared with a length that isn't positive
+    InvalidArrayLength(i32),
+    /// A case label uses something other than constants
+    NotConstant,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::NonGlobalFunction(id) => {
+                write!(f, "function with identifier {} found in local scope", id)
+            }
+            Error::NameCollision(id) => write!(
+                f,
+                "identifier {} cannot be declared because it is already in use",
+                id
+            ),
+            Error::MissingMain => write!(f, "program is missing main function"),
+            Error::InvalidReturn => write!(f, "function must return a value"),
+            Error::InvalidSubroutineParameters => write!(f, "invalid subroutine parameters"),
+            Error::DivisionByZero => write!(f, "cannot divide by zero"),
+            Error::MissingFunction(id) => {
+                write!(f, "function with identifier {} could not be found", id)
+            }
+            Error::BreakOutsideLoop => {
+                write!(f, "break statement found outside of a loop or switch")
+            }
+            Error::ContinueOutsideLoop => {
+                write!(f, "continue statement found outside of a loop")
+            }
+            Error::MissingVariable(id) => {
+                write!(f, "identifier {} could not be found in local scope", id)
+            }
+            Error::InvalidAssign => write!(f, "only variables can be assigned to"),
+            Error::IncompatibleTypes => write!(f, "expressions use incompatible types"),
+            Error::NotAnArray(id) => {
+                write!(
+                    f,
+                    "identifier {} cannot be indexed because it is not an array",
+                    id
+                )
+            }
+            Error::DuplicateCase(Some(value)) => {
+                write!(
+                    f,
+                    "case {} appears more than once in the same switch",
+                    value
+                )
+            }
+            Error::DuplicateCase(None) => {
+                write!(f, "default case appears more than once in the same switch")
+            }
+            Error::InvalidArrayLength(length) => {
+                write!(f, "array length {} is not positive", length)
+            }
+            Error::NotConstant => write!(f, "case label is not a constant expression"),
+        }
+    }
+}
+
+impl Coded for Error {
+    fn code(&self) -> &'static str {
+        match self {
+            Error::MissingVariable(_) => "T0301",
+            Error::MissingFunction(_) => "T0302",
+            Error::MissingMain => "T0303",
+            Error::NameCollision(_) => "T0304",
+            Error::NonGlobalFunction(_) => "T0305",
+            Error::InvalidReturn => "T0306",
+            Error::InvalidSubroutineParameters => "T0307",
+            Error::DivisionByZero => "T0308",
+            Error::InvalidAssign => "T0309",
+            Error::IncompatibleTypes => "T0310",
+            Error::NotAnArray(_) => "T0313",
+            Error::DuplicateCase(_) => "T0314",
+            Error::BreakOutsideLoop => "T0315",
+            Error::ContinueOutsideLoop => "T0316",
+            Error::InvalidArrayLength(_) => "T0317",
+            Error::NotConstant => "T0318",
+        }
+    }
+}
+
+impl Error {
+    /// Give the error the span it was found at
+    fn at(self, span: Span) -> Spanned<Self> {
+        Spanned::new(self, span)
+    }
+}
+
+/// Short-hand version of [Result], where errors are given the span they were found at
+type Result<T> = std::result::Result<T, Spanned<Error>>;
+
+/// Kinds of symbols in the symbol table
+#[derive(Debug, Clone)]
+enum Symbol {
+    /// Variables, along with their type
+    Var(Type),
+    /// Functions, along with their parameter and return types
+    Func(Vec<Type>, Type),
+}
+
+/// Individual entries in the symbol table
+#[derive(Debug, Clone)]
+struct TableEntry {
+    /// where the symbol is stored
+    storage: Storage,
+    /// whether this is a function or variable
+    symbol: Symbol,
+}
+
+/// The symbols declared in a single scope
+#[derive(Debug, Default)]
+struct Scope {
+    /// symbols in the order they were declared
+    symbols: Vec<(String, TableEntry)>,
+    /// position of each symbol in `symbols`
+    indices: HashMap<String, usize>,
+    /// first available offset to be used
+    current_offset: usize,
+}
+
+/// The symbol table itself, as a stack of scopes
+#[derive(Debug)]
+struct SymbolTable {
+    /// scopes from the global scope (first) to the innermost scope (last)
+    scopes: Vec<Scope>,
+}
+
+impl SymbolTable {
+    /// Create the top-level, global symbol table
+    fn new_global() -> Self {
+        Self {
+            scopes: vec![Scope::default()],
+        }
+    }
+
+    /// enter a new scope, whose offsets continue on from the enclosing scope
+    fn push_scope(&mut self) {
+        // functions have their own local variables, starting at 0
+        let current_offset = match self.scopes.len() {
+            1 => 0,
+            _ => self.current_offset(),
+        };
+
+        self.scopes.push(Scope {
+            current_offset,
+            ..Default::default()
+        });
+    }
+
+    /// leave the innermost scope, freeing its offsets to be used again
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// first available offset in the innermost scope
+    fn current_offset(&self) -> usize {
+        self.scopes.last().unwrap().current_offset
+    }
+
+    /// attempt to add a symbol to the innermost scope
+    fn insert(&mut self, id: &str, entry: TableEntry, span: Span) -> Result<()> {
+        let scope = self.scopes.last_mut().unwrap();
+
+        // cannot reuse name in the same scope
+        if scope.indices.contains_key(id) {
+            return Err(Error::NameCollision(id.to_owned()).at(span));
+        }
+
+        scope.indices.insert(id.to_owned(), scope.symbols.len());
+        scope.symbols.push((id.to_owned(), entry));
+
+        Ok(())
+    }
+
+    /// find the innermost declaration of a symbol
+    fn lookup(&self, id: &str) -> Option<&TableEntry> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.indices.get(id).map(|i| &scope.symbols[*i].1))
+    }
+
+    /// attempt to make a new function in the table
+    fn new_func(
+        &mut self,
+        id: &str,
+        params: Vec<Type>,
+        return_type: Type,
+        span: Span,
+    ) -> Result<()> {
+        // must be global scope
+        if self.scopes.len() != 1 {
+            return Err(Error::NonGlobalFunction(id.to_owned()).at(span));
+        }
+
+        self.insert(
+            id,
+            TableEntry {
+                storage: Storage::Static,
+                symbol: Symbol::Func(params, return_type),
+            },
+            span,
+        )
+    }
+
+    /// attempt to make a new variable in the table
+    fn new_var(&mut self, id: &str, var_type: Type, span: Span) -> Result<Variable> {
+        // globals are static, locals take up the next offset
+        let storage = if self.scopes.len() == 1 {
+            Storage::Static
+        } else {
+            Storage::Local(self.current_offset())
+        };
+
+        self.insert(
+            id,
+            TableEntry {
+                storage,
+                symbol: Symbol::Var(var_type),
+            },
+            span,
+        )?;
+
+        if let Storage::Local(_) = storage {
+            self.scopes.last_mut().unwrap().current_offset += 1;
+        }
+
+        Ok(Variable {
+            name: id.to_owned(),
+            storage,
+            var_type,
+            span,
+        })
+    }
+
+    /// determine whether a function exists and return its parameter and return types
+    fn get_function(&self, id: &str, span: Span) -> Result<(&[Type], Type)> {
+        if let Some(Symbol::Func(params, return_type)) = self.lookup(id).map(|e| &e.symbol) {
+            return Ok((params, *return_type));
+        }
+
+        Err(Error::MissingFunction(id.to_owned()).at(span))
+    }
+
+    /// determine whether a variable exists and return where it is stored
+    fn get_variable(&self, id: &str, span: Span) -> Result<Variable> {
+        if let Some(e) = self.lookup(id) {
+            if let Symbol::Var(var_type) = e.symbol {
+                return Ok(Variable {
+                    name: id.to_owned(),
+                    storage: e.storage,
+                    var_type,
+                    span,
+                });
+            }
+        }
+
+        Err(Error::MissingVariable(id.to_owned()).at(span))
+    }
+}
+
+/// State kept while checking a function
+struct FunctionState {
+    /// whether to print the symbol table of each block
+    dump_table: bool,
+    /// number of loops around the current statement
+    loop_depth: usize,
+    /// number of switch statements around the current statement
+    switch_depth: usize,
+    /// highest local offset used so far, plus one
+    locals: usize,
+    /// type returned by the function
+    return_type: Type,
+}
+
+impl FunctionState {
+    /// Declare a local variable, keeping track of the offsets used
+    fn declare(
+        &mut self,
+        table: &mut SymbolTable,
+        id: &str,
+        var_type: Type,
+        span: Span,
+    ) -> Result<Variable> {
+        let var = table.new_var(id, var_type, span)?;
+        self.locals = self.locals.max(table.current_offset());
+        Ok(var)
+    }
+}
+
+/// Check a parsed program, resolving identifiers and giving every expression a type
+///
+/// # Errors
+///
+/// Generates semantic errors in the AST, see [Error].
+pub fn analyze(
+    ast: &ast::Program,
+    source: &Source,
+    dump_table: bool,
+) -> std::result::Result<Program, MaybeContext<Error>> {
+    let program = check_program(ast, dump_table).map_err(|e| source.context(e.node, e.span))?;
+
+    // a missing function has nowhere to point to
+    if !program
+        .functions
+        .iter()
+        .any(|function| function.name == "main")
+    {
+        return Err(Error::MissingMain.into());
+    }
+
+    Ok(program)
+}
+
+/// Check every definition in a program
+fn check_program(ast: &ast::Program, dump_table: bool) -> Result<Program> {
+    let mut symbol_table = SymbolTable::new_global();
+    let mut globals = Vec::new();
+    let mut functions = Vec::new();
+
+    // declare every function up front so that calls may refer to functions defined later
+    for def in ast.0.iter() {
+        if let ast::DefinitionKind::Func(id, return_type, args, _) = &def.kind {
+            let params = args
+                .iter()
+                .flat_map(|(ids, param_type, _)| ids.iter().map(|_| Type::from(*param_type)))
+                .collect::<Vec<_>>();
+
+            // main must have signature int main()
+            if id == "main" && !params.is_empty() {
+                return Err(Error::InvalidSubroutineParameters.at(def.span));
+            }
+
+            symbol_table.new_func(id, params, (*return_type).into(), def.span)?;
+        }
+    }
+
+    // variables can only be used after they are defined
+    for def in ast.0.iter() {
+        match &def.kind {
+            ast::DefinitionKind::Func(id, return_type, args, body) => {
+                symbol_table.push_scope();
+                let mut state = FunctionState {
+                    dump_table,
+                    loop_depth: 0,
+                    switch_depth: 0,
+                    locals: 0,
+                    return_type: (*return_type).into(),
+                };
+
+                // parameters occupy the first local variable offsets, in order
+                let mut params = Vec::new();
+                for (ids, param_type, span) in args {
+                    for id in ids {
+                        params.push(state.declare(
+                            &mut symbol_table,
+                            &id.node,
+                            check_type(*param_type, *span)?,
+                            id.span,
+                        )?);
+                    }
+                }
+
+                let body = check_statement(body, &mut symbol_table, &mut state)?;
+                symbol_table.pop_scope();
+
+                functions.push(Function {
+                    name: id.to_owned(),
+                    return_type: (*return_type).into(),
+                    params,
+                    locals: state.locals,
+                    body,
+                    span: def.span,
+                });
+            }
+            ast::DefinitionKind::Var(ids, var_type) => {
+                for id in ids {
+                    globals.push(symbol_table.new_var(
+                        &id.node,
+                        check_type(*var_type, def.span)?,
+                        id.span,
+                    )?);
+                }
+            }
+        }
+    }
+
+    Ok(Program { globals, functions })
+}
+
+/// Convert a declared type, checking that arrays have a positive length
+fn check_type(var_type: ast::Type, span: Span) -> Result<Type> {
+    match var_type {
+        ast::Type::IntArray(length @ ..=0) | ast::Type::CharArray(length @ ..=0) => {
+            Err(Error::InvalidArrayLength(length).at(span))
+        }
+        var_type => Ok(var_type.into()),
+    }
+}
+
+/// Check a statement in the given scope
+fn check_statement(
+    statement: &ast::Statement,
+    scope: &mut SymbolTable,
+    state: &mut FunctionState,
+) -> Result<Statement> {
+    let span = statement.span;
+    let statement = match &statement.kind {
+        ast::StatementKind::Expr(e) => Statement::Expr(check_integral(e, scope)?),
+        ast::StatementKind::Break => {
+            if state.loop_depth == 0 && state.switch_depth == 0 {
+                return Err(Error::BreakOutsideLoop.at(span));
+            }
+
+            Statement::Break
+        }
+        ast::StatementKind::Continue => {
+            if state.loop_depth == 0 {
+                return Err(Error::ContinueOutsideLoop.at(span));
+            }
+
+            Statement::Continue
+        }
+        ast::StatementKind::Block(vars, statements) => {
+            // create a new scope, which is dropped along with its offsets at the end of the block
+            scope.push_scope();
+            let block = check_block(vars, statements, scope, state);
+            scope.pop_scope();
+
+            block?
+        }
+        ast::StatementKind::If(condition, statement, else_statement) => Statement::If(
+            check_integral(condition, scope)?,
+            Box::new(check_statement(statement, scope, state)?),
+            match else_statement {
+                Some(else_statement) => {
+                    Some(Box::new(check_statement(else_statement, scope, state)?))
+                }
+                None => None,
+            },
+        ),
+        ast::StatementKind::Null => Statement::Null,
+        ast::StatementKind::Return(Some(e)) => {
+            Statement::Return(convert(check_integral(e, scope)?, state.return_type))
+        }
+        ast::StatementKind::Return(None) => return Err(Error::InvalidReturn.at(span)),
+        ast::StatementKind::While(condition, statement) => {
+            let condition = check_integral(condition, scope)?;
+
+            state.loop_depth += 1;
+            let statement = check_statement(statement, scope, state);
+            state.loop_depth -= 1;
+
+            Statement::While(condition, Box::new(statement?))
+        }
+        ast::StatementKind::DoWhile(statement, condition) => {
+            state.loop_depth += 1;
+            let statement = check_statement(statement, scope, state);
+            state.loop_depth -= 1;
+
+            Statement::DoWhile(Box::new(statement?), check_integral(condition, scope)?)
+        }
+        ast::StatementKind::For(init, condition, step, statement) => {
+            let check = |e: &Option<Box<ast::Expression>>| {
+                e.as_deref()
+                    .map(|e| check_integral(e, scope).map(Box::new))
+                    .transpose()
+            };
+            let init = check(init)?;
+            let condition = check(condition)?;
+            let step = check(step)?;
+
+            state.loop_depth += 1;
+            let statement = check_statement(statement, scope, state);
+            state.loop_depth -= 1;
+
+            Statement::For(init, condition, step, Box::new(statement?))
+        }
+        ast::StatementKind::Switch(expression, cases) => {
+            let expression = check_integral(expression, scope)?;
+
+            state.switch_depth += 1;
+            let cases = check_cases(cases, scope, state);
+            state.switch_depth -= 1;
+
+            Statement::Switch(expression, cases?)
+        }
+        ast::StatementKind::Read(ids) => Statement::Read(
+            ids.iter()
+                .map(|id| {
+                    let var = scope.get_variable(&id.node, id.span)?;
+
+                    // only numbers can be read
+                    if !var.var_type.is_integral() {
+                        return Err(Error::IncompatibleTypes.at(id.span));
+                    }
+
+                    Ok(var)
+                })
+                .collect::<Result<_>>()?,
+        ),
+        ast::StatementKind::Write(expressions) => Statement::Write(
+            expressions
+                .iter()
+                .map(|e| {
+                    let e = check_expression(e, scope)?;
+
+                    // arrays can only be written an element at a time
+                    if e.expr_type.element().is_some() {
+                        return Err(Error::IncompatibleTypes.at(e.span));
+                    }
+
+                    Ok(e)
+                })
+                .collect::<Result<_>>()?,
+        ),
+        ast::StatementKind::Newline => Statement::Newline,
+    };
+
+    Ok(statement)
+}
+
+/// Check the declarations and statements of a block in its own scope
+fn check_block(
+    vars: &[ast::VarDef],
+    statements: &[ast::Statement],
+    scope: &mut SymbolTable,
+    state: &mut FunctionState,
+) -> Result<Statement> {
+    // add each variable identifier to the scope
+    let mut declared = Vec::new();
+    for (ids, var_type, span) in vars {
+        for id in ids {
+            declared.push(state.declare(
+                scope,
+                &id.node,
+                check_type(*var_type, *span)?,
+                id.span,
+            )?);
+        }
+    }
+
+    // print the symbol table
+    if state.dump_table {
+        println!("{}", scope);
+    }
+
+    let statements = statements
+        .iter()
+        .map(|statement| check_statement(statement, scope, state))
+        .collect::<Result<_>>()?;
+
+    Ok(Statement::Block(declared, statements))
+}
+
+/// Check the cases of a switch statement, whose values must all be different
+fn check_cases(
+    cases: &[ast::SwitchCase],
+    scope: &mut SymbolTable,
+    state: &mut FunctionState,
+) -> Result<Vec<SwitchCase>> {
+    let mut values = HashSet::new();
+    let mut checked = Vec::new();
+
+    for case in cases {
+        let value = case
+            .value
+            .as_ref()
+            .map(|value| case_value(value, scope))
+            .transpose()?;
+
+        if !values.insert(value) {
+            return Err(Error::DuplicateCase(value).at(case.span));
+        }
+
+        checked.push(SwitchCase {
+            value,
+            statements: case
+                .statements
+                .iter()
+                .map(|statement| check_statement(statement, scope, state))
+                .collect::<Result<_>>()?,
+        });
+    }
+
+    Ok(checked)
+}
+
+/// Value of the constant expression in a case label, where chars are their character code
+fn case_value(expression: &ast::Expression, scope: &SymbolTable) -> Result<i32> {
+    let expression = check_integral(expression, scope)?;
+    constant(&expression)
+}
+
+/// Fold an expression made of constants into its value, the same as if it were run.
+/// Every operand is folded, even the ones that wouldn't be run.
+fn constant(expression: &Expression) -> Result<i32> {
+    let value = match &expression.kind {
+        ExpressionKind::Number(n) => *n,
+        ExpressionKind::CharLiteral(c) => *c as i32,
+        ExpressionKind::Expr(op, lhs, rhs) => op
+            .apply(constant(lhs)?, constant(rhs)?)
+            .ok_or_else(|| Error::DivisionByZero.at(rhs.span))?,
+        ExpressionKind::Minus(e) => constant(e)?.wrapping_neg(),
+        ExpressionKind::Not(e) => i32::from(constant(e)? == 0),
+        ExpressionKind::BitNot(e) => !constant(e)?,
+        ExpressionKind::ToChar(e) => i32::from(constant(e)? as u16),
+        ExpressionKind::Conditional(condition, true_exp, false_exp) => {
+            let (condition, true_value, false_value) = (
+                constant(condition)?,
+                constant(true_exp)?,
+                constant(false_exp)?,
+            );
+            if condition != 0 {
+                true_value
+            } else {
+                false_value
+            }
+        }
+        _ => return Err(Error::NotConstant.at(expression.span)),
+    };
+
+    Ok(value)
+}
+
+/// Convert a number to the type it is stored as. Chars become ints without any
+/// conversion, but ints must be narrowed to become chars.
+fn convert(expression: Expression, to: Type) -> Expression {
+    if to == Type::Char && expression.expr_type != Type::Char {
+        let span = expression.span;
+        Expression {
+            kind: ExpressionKind::ToChar(Box::new(expression)),
+            expr_type: Type::Char,
+            span,
+        }
+    } else {
+        expression
+    }
+}
+
+/// Find an array variable, along with the type of its elements
+fn get_array(id: &str, span: Span, scope: &SymbolTable) -> Result<(Variable, Type)> {
+    let var = scope.get_variable(id, span)?;
+
+    match var.var_type.element() {
+        Some(element) => Ok((var, element)),
+        None => Err(Error::NotAnArray(id.to_owned()).at(span)),
+    }
+}
+
+/// Check an expression whose value must be a number
+fn check_integral(expression: &ast::Expression, scope: &SymbolTable) -> Result<Expression> {
+    let expression = check_expression(expression, scope)?;
+
+    if !expression.expr_type.is_integral() {
+        return Err(Error::IncompatibleTypes.at(expression.span));
+    }
+
+    Ok(expression)
+}
+
+/// Check an expression in the given scope, finding its type
+fn check_expression(expression: &ast::Expression, scope: &SymbolTable) -> Result<Expression> {
+    let span = expression.span;
+    let (kind, expr_type) = match &expression.kind {
+        ast::ExpressionKind::Number(n) => (ExpressionKind::Number(*n), Type::Int),
+        ast::ExpressionKind::Identifier(id) => {
+            let var = scope.get_variable(id, span)?;
+            let var_type = var.var_type;
+            (ExpressionKind::Variable(var), var_type)
+        }
+        ast::ExpressionKind::CharLiteral(c) => (ExpressionKind::CharLiteral(*c), Type::Char),
+        ast::ExpressionKind::StringLiteral(s) => {
+            (ExpressionKind::StringLiteral(s.to_owned()), Type::String)
+        }
+        ast::ExpressionKind::FuncCall(id, args) => {
+            let (params, return_type) = scope.get_function(id, span)?;
+
+            if params.len() != args.len() {
+                return Err(Error::InvalidSubroutineParameters.at(span));
+            }
+
+            let args = args
+                .iter()
+                .zip(params)
+                .map(|(arg, param)| Ok(convert(check_integral(arg, scope)?, *param)))
+                .collect::<Result<_>>()?;
+
+            (ExpressionKind::FuncCall(id.to_owned(), args), return_type)
+        }
+        ast::ExpressionKind::Index(id, index) => {
+            let (var, element) = get_array(id, expression.span, scope)?;
+            let index = check_integral(index, scope)?;
+
+            (ExpressionKind::Index(var, Box::new(index)), element)
+        }
+        ast::ExpressionKind::Expr(ast::Operator::Assign, lhs, rhs) => match &lhs.kind {
+            ast::ExpressionKind::Identifier(id) => {
+                let var = scope.get_variable(id, lhs.span)?;
+                let var_type = var.var_type;
+
+                // arrays can only be assigned an element at a time
+                if !var_type.is_integral() {
+                    return Err(Error::IncompatibleTypes.at(lhs.span));
+                }
+
+                let rhs = convert(check_integral(rhs, scope)?, var_type);
+
+                (ExpressionKind::Assign(var, Box::new(rhs)), var_type)
+            }
+            ast::ExpressionKind::Index(id, index) => {
+                let (var, element) = get_array(id, lhs.span, scope)?;
+                let index = check_integral(index, scope)?;
+                let rhs = convert(check_integral(rhs, scope)?, element);
+
+                (
+                    ExpressionKind::AssignIndex(var, Box::new(index), Box::new(rhs)),
+                    element,
+                )
+            }
+            // lhs must be an id or an element of an array
+            _ => return Err(Error::InvalidAssign.at(lhs.span)),
+        },
+        ast::ExpressionKind::Expr(op, lhs, rhs) => {
+            // dividing by a literal zero can be caught early
+            if matches!(op, ast::Operator::Div | ast::Operator::Mod)
+                && matches!(&rhs.kind, ast::ExpressionKind::Number(0))
+            {
+                return Err(Error::DivisionByZero.at(rhs.span));
+            }
+
+            let lhs = check_integral(lhs, scope)?;
+            let rhs = check_integral(rhs, scope)?;
+
+            (
+                ExpressionKind::Expr(*op, Box::new(lhs), Box::new(rhs)),
+                Type::Int,
+            )
+        }
+        ast::ExpressionKind::Minus(e) => (
+            ExpressionKind::Minus(Box::new(check_integral(e, scope)?)),
+            Type::Int,
+        ),
+        ast::ExpressionKind::Not(e) => (
+            ExpressionKind::Not(Box::new(check_integral(e, scope)?)),
+            Type::Int,
+        ),
+        ast::ExpressionKind::BitNot(e) => (
+            ExpressionKind::BitNot(Box::new(check_integral(e, scope)?)),
+            Type::Int,
+        ),
+        ast::ExpressionKind::Conditional(condition, true_exp, false_exp) => {
+            let condition = check_integral(condition, scope)?;
+            let true_exp = check_expression(true_exp, scope)?;
+            let false_exp = check_expression(false_exp, scope)?;
+
+            // both sides are numbers (only chars if both are chars), or both are strings
+            let expr_type = match (true_exp.expr_type, false_exp.expr_type) {
+                (Type::Char, Type::Char) => Type::Char,
+                (Type::String, Type::String) => Type::String,
+                (t, f) if t.is_integral() && f.is_integral() => Type::Int,
+                (t, _) if !t.is_integral() && t != Type::String => {
+                    return Err(Error::IncompatibleTypes.at(true_exp.span))
+                }
+                _ => return Err(Error::IncompatibleTypes.at(false_exp.span)),
+            };
+
+            (
+                ExpressionKind::Conditional(
+                    Box::new(condition),
+                    Box::new(true_exp),
+                    Box::new(false_exp),
+                ),
+                expr_type,
+            )
+        }
+    };
+
+    Ok(Expression {
+        kind,
+        expr_type,
+        span,
+    })
+}
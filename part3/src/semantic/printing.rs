@@ -0,0 +1,50 @@
+//! EGRE 591 part3 - Nathan Rowan and Trevin Vaughan
+//!
+//! Printing of the symbol table for `--symbol` dumps. Scopes are printed from the innermost
+//! outwards (the order names are looked up in), with symbols in the order they were declared.
+
+use std::fmt::Display;
+
+use super::*;
+
+/// Indentation of symbols inside of a scope
+const INDENT: &str = "    ";
+
+impl Display for Storage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Storage::Local(offset) => write!(f, "local {}", offset),
+            Storage::Static => write!(f, "static"),
+        }
+    }
+}
+
+impl Display for TableEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.symbol {
+            Symbol::Var(var_type) => write!(f, "{} ({})", var_type, self.storage),
+            Symbol::Func(params, return_type) => {
+                let params = params.iter().map(Type::to_string).collect::<Vec<_>>();
+                write!(f, "{} function({})", return_type, params.join(", "))
+            }
+        }
+    }
+}
+
+impl Display for SymbolTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (depth, scope) in self.scopes.iter().enumerate().rev() {
+            if depth == 0 {
+                writeln!(f, "global scope:")?;
+            } else {
+                writeln!(f, "scope {}:", depth)?;
+            }
+
+            for (id, entry) in &scope.symbols {
+                writeln!(f, "{}{}: {}", INDENT, id, entry)?;
+            }
+        }
+
+        Ok(())
+    }
+}
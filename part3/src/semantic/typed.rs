@@ -0,0 +1,217 @@
+//! EGRE 591 part3 - Nathan Rowan and Trevin Vaughan
+//!
+//! The typed AST, which is created by semantic analysis. Every identifier is resolved
+//! to where its variable is stored and every expression has a type.
+
+use std::fmt::Display;
+
+use crate::{
+    parser::ast::{self, Identifier, Operator},
+    span::Span,
+};
+
+/// Types of values in a checked program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    /// Integers, which are also used as booleans
+    Int,
+    /// Characters
+    Char,
+    /// String literals, which can only be written
+    String,
+    /// Arrays of integers, with their length
+    IntArray(u32),
+    /// Arrays of characters, with their length
+    CharArray(u32),
+}
+
+impl Type {
+    /// Whether values of this type are numbers (chars are numbers, like in C)
+    pub fn is_integral(self) -> bool {
+        matches!(self, Type::Int | Type::Char)
+    }
+
+    /// The type of the elements, if this is an array type
+    pub fn element(self) -> Option<Type> {
+        match self {
+            Type::IntArray(_) => Some(Type::Int),
+            Type::CharArray(_) => Some(Type::Char),
+            _ => None,
+        }
+    }
+
+    /// The length, if this is an array type
+    pub fn length(self) -> Option<u32> {
+        match self {
+            Type::IntArray(length) | Type::CharArray(length) => Some(length),
+            _ => None,
+        }
+    }
+}
+
+/// Array lengths must have been checked to be positive first
+impl From<ast::Type> for Type {
+    fn from(value: ast::Type) -> Self {
+        match value {
+            ast::Type::Int => Type::Int,
+            ast::Type::Char => Type::Char,
+            ast::Type::IntArray(length) => Type::IntArray(length as u32),
+            ast::Type::CharArray(length) => Type::CharArray(length as u32),
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Char => write!(f, "char"),
+            Type::String => write!(f, "string"),
+            Type::IntArray(length) => write!(f, "int[{}]", length),
+            Type::CharArray(length) => write!(f, "char[{}]", length),
+        }
+    }
+}
+
+/// Places where variables can be stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Storage {
+    /// Local variables, with their offset, e.g. r0 in Arm or load_0 in the jvm
+    Local(usize),
+    /// Global symbols, e.g. static fields and methods in the jvm
+    Static,
+}
+
+/// A use of a variable, resolved to its declaration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variable {
+    /// name of the variable
+    pub name: Identifier,
+    /// where the variable is stored
+    pub storage: Storage,
+    /// type of the variable
+    pub var_type: Type,
+    /// where the variable was declared or used
+    pub span: Span,
+}
+
+/// The program, aka the top level of the typed AST
+#[derive(Debug, Clone)]
+pub struct Program {
+    /// global variables, which are all static
+    pub globals: Vec<Variable>,
+    /// functions, in the order they were defined
+    pub functions: Vec<Function>,
+}
+
+/// A function definition
+#[derive(Debug, Clone)]
+pub struct Function {
+    /// name of the function
+    pub name: Identifier,
+    /// type of the returned value
+    pub return_type: Type,
+    /// parameters, which take up the first local offsets
+    pub params: Vec<Variable>,
+    /// number of local offsets used by the parameters and every local variable
+    pub locals: usize,
+    /// body of the function
+    pub body: Statement,
+    /// the whole definition of the function
+    pub span: Span,
+}
+
+/// All statements allowed in the typed AST
+#[derive(Debug, Clone)]
+pub enum Statement {
+    /// An expression statement, whose value is discarded
+    Expr(Expression),
+    /// A break statement, which is always inside of a loop or switch (see
+    /// [Error::BreakOutsideLoop](super::Error::BreakOutsideLoop))
+    Break,
+    /// A continue statement, which is always inside of a loop (see
+    /// [Error::ContinueOutsideLoop](super::Error::ContinueOutsideLoop))
+    Continue,
+    /// A block with the variables it declares and more statements
+    Block(Vec<Variable>, Vec<Statement>),
+    /// An if statement
+    If(Expression, Box<Statement>, Option<Box<Statement>>),
+    /// A null statement
+    Null,
+    /// A return statement
+    Return(Expression),
+    /// A while statement
+    While(Expression, Box<Statement>),
+    /// A do-while statement, with its body before its condition
+    DoWhile(Box<Statement>, Expression),
+    /// A for statement, whose missing condition is always true
+    For(
+        Option<Box<Expression>>,
+        Option<Box<Expression>>,
+        Option<Box<Expression>>,
+        Box<Statement>,
+    ),
+    /// A switch statement, with its cases in the order they appear
+    Switch(Expression, Vec<SwitchCase>),
+    /// A read statement
+    Read(Vec<Variable>),
+    /// A write statement
+    Write(Vec<Expression>),
+    /// A newline statement
+    Newline,
+}
+
+/// A case of a switch statement, whose statements fall through to the next case
+#[derive(Debug, Clone)]
+pub struct SwitchCase {
+    /// the value compared against, or [None] for the default case
+    pub value: Option<i32>,
+    /// statements run when the case matches
+    pub statements: Vec<Statement>,
+}
+
+/// An expression along with its type
+#[derive(Debug, Clone)]
+pub struct Expression {
+    /// what the expression does
+    pub kind: ExpressionKind,
+    /// type of the value of the expression
+    pub expr_type: Type,
+    /// where the expression is in the source file
+    pub span: Span,
+}
+
+/// All expressions allowed in the typed AST
+#[derive(Debug, Clone)]
+pub enum ExpressionKind {
+    /// A number
+    Number(i32),
+    /// A character literal
+    CharLiteral(char),
+    /// A string literal
+    StringLiteral(String),
+    /// A variable
+    Variable(Variable),
+    /// A function call, including an identifier and a list of input expressions
+    FuncCall(Identifier, Vec<Expression>),
+    /// An assignment, whose value is the assigned value
+    Assign(Variable, Box<Expression>),
+    /// An element of an array, including the array and the index of the element
+    Index(Variable, Box<Expression>),
+    /// An assignment to an element of an array, including the array, the index and the
+    /// assigned value, which is also the value of the assignment
+    AssignIndex(Variable, Box<Expression>, Box<Expression>),
+    /// A binary operation with a left and right hand sub-expression (never an assignment)
+    Expr(Operator, Box<Expression>, Box<Expression>),
+    /// Unary negation on numbers
+    Minus(Box<Expression>),
+    /// Unary negation on booleans
+    Not(Box<Expression>),
+    /// Bitwise complement on numbers
+    BitNot(Box<Expression>),
+    /// Converts a number to a char, keeping its lowest 16 bits like a JVM char
+    ToChar(Box<Expression>),
+    /// A conditional expression, including the condition and the values when it is true
+    /// and when it is false (only one of which is evaluated)
+    Conditional(Box<Expression>, Box<Expression>, Box<Expression>),
+}
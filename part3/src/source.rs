@@ -0,0 +1,40 @@
+//! EGRE 591 part3 - Nathan Rowan and Trevin Vaughan
+//!
+//! Source files kept in memory after parsing. Errors found in later passes only have a
+//! [Span], so the lines of the file are needed to give them [Context].
+
+use std::{fmt::Display, fs::read_to_string, io::Error, path::Path};
+
+use crate::{context::Context, span::Span};
+
+/// The lines of a source file, along with its name
+pub struct Source {
+    /// Lines of the file, without line endings
+    lines: Vec<String>,
+    /// Name of the file, as given on the command line
+    file_name: String,
+}
+
+impl Source {
+    /// Read a whole source file into memory
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file cannot be read.
+    pub fn new(path: &Path) -> Result<Self, Error> {
+        let lines = read_to_string(path)?.lines().map(str::to_owned).collect();
+
+        Ok(Self {
+            lines,
+            file_name: path.to_string_lossy().to_string(),
+        })
+    }
+
+    /// Get context for a warning or error covering a span
+    pub fn context<T: Display>(&self, t: T, span: Span) -> Context<T> {
+        // spans at the very end of the file may be past the last line
+        let line = self.lines.get(span.start.line).cloned().unwrap_or_default();
+
+        Context::new(t, line, span, self.file_name.clone())
+    }
+}
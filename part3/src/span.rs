@@ -0,0 +1,50 @@
+//! EGRE 591 part3 - Nathan Rowan and Trevin Vaughan
+//!
+//! Locations in a source file. Tokens are given a [Span] by the scanner, and the parser
+//! combines these into spans for each node of the AST.
+
+/// A place in a source file, between two characters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    /// line number, starting at 0
+    pub line: usize,
+    /// place along the line, starting at 0
+    pub column: usize,
+}
+
+/// A range of a source file, from the start of the first character to the end of the last
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    /// position of the first character
+    pub start: Position,
+    /// position after the last character
+    pub end: Position,
+}
+
+impl Span {
+    /// Construct a new span
+    pub fn new(start: Position, end: Position) -> Self {
+        Self { start, end }
+    }
+
+    /// Span from the start of this span to the end of another, which must come after it
+    pub fn to(self, other: Span) -> Self {
+        Self::new(self.start, other.end)
+    }
+}
+
+/// A value along with the part of the source file it came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spanned<T> {
+    /// the value itself
+    pub node: T,
+    /// where the value is in the source file
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    /// Construct a new spanned value
+    pub fn new(node: T, span: Span) -> Self {
+        Self { node, span }
+    }
+}
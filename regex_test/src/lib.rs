@@ -4,8 +4,8 @@
 //! . (any)
 //! ^ (not)
 //! ? (maybe)
-//! * (repeating)
-//! + (at least one)
+//! \* (repeating)
+//! \+ (at least one)
 //! () (group)
 //! [] (character class)
 //! \ (escape metalanguage)